@@ -18,9 +18,11 @@ extern crate pushrod;
 use piston_window::*;
 use pushrod::core::main::*;
 use pushrod::widget::box_widget::*;
+use pushrod::widget::builder::*;
 use pushrod::widget::text_widget::*;
 use pushrod::widget::timer_widget::*;
 use pushrod::widget::widget::*;
+use pushrod::widgets;
 
 fn main() {
     let window: PistonWindow = WindowSettings::new("Pushrod Window", [800, 600])
@@ -31,62 +33,58 @@ fn main() {
     let factory: GfxFactory = window.factory.clone();
     let mut prod: Pushrod = Pushrod::new(window);
 
-    let mut base_widget = BaseWidget::new();
-    base_widget.set_origin(50, 80);
-    base_widget.set_size(200, 200);
-    base_widget.set_color([0.5, 0.5, 0.5, 1.0]);
-    base_widget.on_mouse_entered(Box::new(|widget_id| {
-        eprintln!("Mouse entered widget {}", widget_id);
-    }));
-    prod.widget_store.add_widget(Box::new(base_widget));
-
-    let mut box_widget = BoxWidget::new();
-    box_widget.set_origin(275, 80);
-    box_widget.set_size(200, 200);
-    box_widget.set_color([0.0, 1.0, 0.0, 1.0]);
-    box_widget.set_border([1.0, 0.0, 0.0, 1.0], 4);
-    prod.widget_store.add_widget(Box::new(box_widget));
-
-    let mut box_1 = BoxWidget::new();
-    box_1.set_origin(500, 80);
-    box_1.set_size(200, 200);
-    box_1.set_color([0.5, 0.5, 1.0, 1.0]);
-    box_1.set_border([0.0, 0.0, 1.0, 1.0], 2);
-    let box_1_id = prod.widget_store.add_widget(Box::new(box_1));
-
-    let mut box_2 = BoxWidget::new();
-    box_2.set_origin(550, 105);
-    box_2.set_size(100, 50);
-    box_2.set_color([0.75, 0.75, 1.0, 1.0]);
-    box_2.set_border([1.0, 0.0, 1.0, 1.0], 1);
-    prod.widget_store
-        .add_widget_to_parent(Box::new(box_2), box_1_id);
-
-    let mut box_3 = BoxWidget::new();
-    box_3.set_origin(550, 205);
-    box_3.set_size(100, 50);
-    box_3.set_color([0.75, 0.75, 1.0, 1.0]);
-    box_3.set_border([1.0, 0.0, 1.0, 1.0], 1);
-    prod.widget_store
-        .add_widget_to_parent(Box::new(box_3), box_1_id);
-
-    let mut timer = TimerWidget::new();
-    timer.set_timeout(1000);
-    timer.set_enabled(true);
-    timer.on_timeout(Box::new(|| eprintln!("Timer.")));
-    prod.widget_store.add_widget(Box::new(timer));
-
     let mut text_widget = TextWidget::new(
         factory,
         "OpenSans-Regular.ttf".to_string(),
         "Welcome to Pushrod!".to_string(),
         32,
     );
-    text_widget.set_origin(8, 8);
-    text_widget.set_size(400, 40);
-    text_widget.set_color([0.75, 0.75, 1.0, 1.0]);
     text_widget.set_text_color([0.0, 0.0, 1.0, 1.0]);
-    prod.widget_store.add_widget(Box::new(text_widget));
+
+    prod.widget_store
+        .add_widgets(
+            widgets![
+                BaseWidget::builder()
+                    .origin(50, 80)
+                    .size(200, 200)
+                    .color([0.5, 0.5, 0.5, 1.0])
+                    .with(|widget| {
+                        widget.on_mouse_entered(Box::new(|widget_id| {
+                            eprintln!("Mouse entered widget {}", widget_id);
+                        }))
+                    }),
+                BoxWidget::builder()
+                    .origin(275, 80)
+                    .size(200, 200)
+                    .color([0.0, 1.0, 0.0, 1.0])
+                    .border([1.0, 0.0, 0.0, 1.0], 4),
+                BoxWidget::builder()
+                    .origin(500, 80)
+                    .size(200, 200)
+                    .color([0.5, 0.5, 1.0, 1.0])
+                    .border([0.0, 0.0, 1.0, 1.0], 2) => [
+                    BoxWidget::builder()
+                        .origin(550, 105)
+                        .size(100, 50)
+                        .color([0.75, 0.75, 1.0, 1.0])
+                        .border([1.0, 0.0, 1.0, 1.0], 1),
+                    BoxWidget::builder()
+                        .origin(550, 205)
+                        .size(100, 50)
+                        .color([0.75, 0.75, 1.0, 1.0])
+                        .border([1.0, 0.0, 1.0, 1.0], 1),
+                ],
+                TimerWidget::builder()
+                    .timeout(1000)
+                    .with(|timer| timer.on_timeout(Box::new(|| eprintln!("Timer.")))),
+                WidgetBuilder::new(text_widget)
+                    .origin(8, 8)
+                    .size(400, 40)
+                    .color([0.75, 0.75, 1.0, 1.0]),
+            ],
+            0,
+        )
+        .unwrap_or_else(|error| panic!("Failed to add widgets: {}", error));
 
 //    prod.add_event_listener_for_window(Box::new(ExampleListener::new()));
 
@@ -0,0 +1,504 @@
+// Animation
+// Tweening of widget configuration values over time
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::types::Color;
+use std::any::Any;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+use crate::core::callbacks::*;
+use crate::core::clock::*;
+use crate::core::point::*;
+use crate::core::widget_store::*;
+use crate::widget::config::*;
+use crate::widget::widget::*;
+
+/// Identifier assigned to an animation.  Use this ID to chain animations, register completion
+/// callbacks, or cancel the animation.
+pub type AnimationId = u32;
+
+/// Easing curves used to control the rate of change of an animation over its duration.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    /// Constant rate of change.
+    Linear,
+
+    /// Quadratic curve, starting slowly and accelerating.
+    EaseInQuad,
+
+    /// Quadratic curve, starting quickly and decelerating.
+    EaseOutQuad,
+
+    /// Quadratic curve, accelerating until halfway, then decelerating.
+    EaseInOutQuad,
+
+    /// Cubic curve, starting slowly and accelerating.
+    EaseInCubic,
+
+    /// Cubic curve, starting quickly and decelerating.
+    EaseOutCubic,
+
+    /// Cubic curve, accelerating until halfway, then decelerating.
+    EaseInOutCubic,
+
+    /// Sine curve, starting slowly and accelerating.
+    EaseInSine,
+
+    /// Sine curve, starting quickly and decelerating.
+    EaseOutSine,
+
+    /// Sine curve, accelerating until halfway, then decelerating.
+    EaseInOutSine,
+}
+
+/// Implementation of the `Easing` curves.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::animation::*;
+/// # fn main() {
+///     assert_eq!(Easing::Linear.apply(0.25), 0.25);
+///     assert_eq!(Easing::EaseInQuad.apply(0.5), 0.25);
+///     assert_eq!(Easing::EaseOutCubic.apply(1.0), 1.0);
+/// # }
+/// ```
+impl Easing {
+    /// Applies the easing curve to `t`, the linear progress of an animation between 0.0 and 1.0.
+    /// Returns the eased progress, which is 0.0 at the start, and 1.0 at the end.
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.max(0.0).min(1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => t * (2.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -1.0 + (4.0 - 2.0 * t) * t
+                }
+            }
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => {
+                let f = t - 1.0;
+
+                f * f * f + 1.0
+            }
+            Easing::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let f = 2.0 * t - 2.0;
+
+                    0.5 * f * f * f + 1.0
+                }
+            }
+            Easing::EaseInSine => 1.0 - (t * PI / 2.0).cos(),
+            Easing::EaseOutSine => (t * PI / 2.0).sin(),
+            Easing::EaseInOutSine => -0.5 * ((PI * t).cos() - 1.0),
+        }
+    }
+}
+
+/// Implementable trait for values that can be animated.  `tween` returns the value at
+/// `progress` of the way between `self` and `to`, where `progress` is 0.0 at `self` and 1.0
+/// at `to`.  Implement this for the value type of your own `ConfigKey` to animate it.
+pub trait Tween: Clone + 'static {
+    /// Returns the value between `self` and `to` at the specified `progress`.
+    fn tween(&self, to: &Self, progress: f64) -> Self;
+}
+
+fn lerp(from: f64, to: f64, progress: f64) -> f64 {
+    from + (to - from) * progress
+}
+
+impl Tween for f64 {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        lerp(*self, *to, progress)
+    }
+}
+
+impl Tween for f32 {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        lerp(*self as f64, *to as f64, progress) as f32
+    }
+}
+
+impl Tween for i32 {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        lerp(*self as f64, *to as f64, progress).round() as i32
+    }
+}
+
+impl Tween for u32 {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        lerp(*self as f64, *to as f64, progress).round() as u32
+    }
+}
+
+impl Tween for u8 {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        lerp(*self as f64, *to as f64, progress).round() as u8
+    }
+}
+
+impl Tween for Point {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        Point {
+            x: self.x.tween(&to.x, progress),
+            y: self.y.tween(&to.y, progress),
+        }
+    }
+}
+
+impl Tween for Size {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        Size {
+            w: self.w.tween(&to.w, progress),
+            h: self.h.tween(&to.h, progress),
+        }
+    }
+}
+
+impl Tween for Color {
+    fn tween(&self, to: &Self, progress: f64) -> Self {
+        [
+            self[0].tween(&to[0], progress),
+            self[1].tween(&to[1], progress),
+            self[2].tween(&to[2], progress),
+            self[3].tween(&to[3], progress),
+        ]
+    }
+}
+
+/// Internal trait used to store animations of different value types together.
+trait Track {
+    /// Captures the starting value of the animation from the widget.
+    fn start(&mut self, widget: &mut dyn Widget);
+
+    /// Applies the value of the animation at the eased `progress` to the widget.
+    fn apply(&self, widget: &mut dyn Widget, progress: f64);
+
+    /// Retrieves the `ConfigKeyId` of the key being animated.
+    fn key_id(&self) -> ConfigKeyId;
+}
+
+/// Typed implementation of a `Track`, animating the value stored under `key`.
+struct TweenTrack<T: Tween> {
+    key: ConfigKey<T>,
+    start_value: Option<T>,
+    target: T,
+}
+
+impl<T: Tween> Track for TweenTrack<T> {
+    fn start(&mut self, widget: &mut dyn Widget) {
+        self.start_value = Some(widget.config().get(self.key));
+    }
+
+    fn apply(&self, widget: &mut dyn Widget, progress: f64) {
+        let value = match self.start_value {
+            Some(ref start_value) if progress < 1.0 => start_value.tween(&self.target, progress),
+            _ => self.target.clone(),
+        };
+
+        // Origin, size and color go through the widget's setters, so widgets that override
+        // them see the animated values.  The key IDs include the value type, so the downcasts
+        // always succeed.
+        let key_id = self.key.id();
+        let any_value: &dyn Any = &value;
+
+        if key_id == CONFIG_ORIGIN.id() {
+            if let Some(origin) = any_value.downcast_ref::<Point>() {
+                widget.set_origin(origin.x, origin.y);
+                return;
+            }
+        } else if key_id == CONFIG_SIZE.id() {
+            if let Some(size) = any_value.downcast_ref::<Size>() {
+                widget.set_size(size.w, size.h);
+                return;
+            }
+        } else if key_id == CONFIG_COLOR.id() {
+            if let Some(color) = any_value.downcast_ref::<Color>() {
+                widget.set_color(*color);
+                return;
+            }
+        }
+
+        set_widget_config(widget, self.key, value);
+    }
+
+    fn key_id(&self) -> ConfigKeyId {
+        self.key.id()
+    }
+}
+
+/// Internal structure used to track a single animation.
+struct Animation {
+    id: AnimationId,
+    widget_id: i32,
+    track: Box<dyn Track>,
+    duration: u64,
+    easing: Easing,
+    after: Option<AnimationId>,
+    started_at: Option<u64>,
+    on_complete: Option<SingleCallback>,
+}
+
+/// This is the `Animator`, which changes `Widget` configuration values from their current value
+/// to a target value over time.  The `Pushrod` run loop owns an `Animator`, and calls `update`
+/// during the update phase of each loop, invalidating only the widgets being animated.
+///
+/// Any configuration value whose type implements `Tween` can be animated, such as those stored
+/// under `CONFIG_ORIGIN`, `CONFIG_SIZE`, `CONFIG_COLOR`, `CONFIG_COLOR_BORDER`,
+/// `CONFIG_BORDER_WIDTH` and `CONFIG_TEXT_COLOR`.
+///
+/// Animations run in parallel, unless they are chained using `animate_after`.  Starting an
+/// animation on a widget's configuration key that is already being animated cancels the running
+/// animation.
+pub struct Animator {
+    clock: Rc<dyn Clock>,
+    next_id: AnimationId,
+    animations: Vec<Animation>,
+}
+
+/// Implementation of the `Animator`.
+///
+/// Example usage, using a `ManualClock` to control the animation:
+/// ```
+/// # use pushrod::core::animation::*;
+/// # use pushrod::core::clock::*;
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::widget::*;
+/// # use pushrod::core::point::*;
+/// # use std::rc::Rc;
+/// # fn main() {
+///     let clock = Rc::new(ManualClock::new());
+///     let mut animator = Animator::with_clock(clock.clone());
+///     let mut widget_store = WidgetStore::new();
+///     let mut base_widget = BaseWidget::new();
+///
+///     base_widget.set_size(100, 100);
+///     let widget_id = widget_store.add_widget(Box::new(base_widget)).unwrap();
+///
+///     // Slide the widget to the right, then grow it once it has arrived.
+///     let slide = animator.animate(widget_id, CONFIG_ORIGIN, Point { x: 200, y: 0 }, 1000,
+///         Easing::Linear);
+///     animator.animate_after(slide, widget_id, CONFIG_SIZE, Size { w: 200, h: 200 }, 500,
+///         Easing::EaseOutQuad);
+///     animator.on_complete(slide, Box::new(|widget_id| eprintln!("Slid widget {}", widget_id)));
+///
+///     // Animations start on the next update.
+///     animator.update(&mut widget_store);
+///
+///     clock.advance(500);
+///     animator.update(&mut widget_store);
+///     assert_eq!(widget_store.widgets[widget_id as usize].widget.get_origin().x, 100);
+///
+///     clock.advance(500);
+///     animator.update(&mut widget_store);
+///     assert_eq!(widget_store.widgets[widget_id as usize].widget.get_origin().x, 200);
+///
+///     clock.advance(500);
+///     animator.update(&mut widget_store);
+///     assert_eq!(widget_store.widgets[widget_id as usize].widget.get_size().w, 200);
+///     assert!(!animator.is_animating(widget_id));
+/// # }
+/// ```
+impl Animator {
+    /// Creates a new `Animator` with no animations, using a `RealClock`.
+    pub fn new() -> Self {
+        Self::with_clock(Rc::new(RealClock::new()))
+    }
+
+    /// Creates a new `Animator` with no animations, reading time from the specified `Clock`.
+    pub fn with_clock(clock: Rc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            next_id: 1,
+            animations: Vec::new(),
+        }
+    }
+
+    fn add_animation(
+        &mut self,
+        widget_id: i32,
+        track: Box<dyn Track>,
+        duration: u64,
+        easing: Easing,
+        after: Option<AnimationId>,
+    ) -> AnimationId {
+        let id = self.next_id;
+
+        self.next_id += 1;
+        self.animations.push(Animation {
+            id,
+            widget_id,
+            track,
+            duration,
+            easing,
+            after,
+            started_at: None,
+            on_complete: None,
+        });
+
+        id
+    }
+
+    /// Animates the configuration value stored under `key` for the widget specified by
+    /// `widget_id`, from its current value to `target`, over `duration` milliseconds.  The
+    /// animation starts on the next update.  Returns the `AnimationId` of the animation.
+    pub fn animate<T: Tween>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        target: T,
+        duration: u64,
+        easing: Easing,
+    ) -> AnimationId {
+        let track = TweenTrack {
+            key,
+            start_value: None,
+            target,
+        };
+
+        self.add_animation(widget_id, Box::new(track), duration, easing, None)
+    }
+
+    /// Animates a configuration value in the same way as `animate`, but only starts once the
+    /// animation specified by `previous` has completed or been cancelled.  Returns the
+    /// `AnimationId` of the animation, which can in turn be chained.
+    pub fn animate_after<T: Tween>(
+        &mut self,
+        previous: AnimationId,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        target: T,
+        duration: u64,
+        easing: Easing,
+    ) -> AnimationId {
+        let track = TweenTrack {
+            key,
+            start_value: None,
+            target,
+        };
+
+        self.add_animation(widget_id, Box::new(track), duration, easing, Some(previous))
+    }
+
+    /// Sets the closure action to be performed when an animation completes.  The closure receives
+    /// the ID of the animated widget.  It is not called if the animation is cancelled.
+    pub fn on_complete(&mut self, id: AnimationId, callback: SingleCallback) {
+        if let Some(animation) = self.animations.iter_mut().find(|x| x.id == id) {
+            animation.on_complete = Some(callback);
+        }
+    }
+
+    /// Cancels an animation, leaving the configuration value at its current point.  Animations
+    /// chained after it start on the next update.  Returns `true` if the animation existed.
+    pub fn cancel(&mut self, id: AnimationId) -> bool {
+        let exists = self.animations.iter().any(|x| x.id == id);
+
+        self.animations.retain(|x| x.id != id);
+
+        exists
+    }
+
+    /// Indicates whether or not the specified widget has any running or pending animations.
+    pub fn is_animating(&self, widget_id: i32) -> bool {
+        self.animations.iter().any(|x| x.widget_id == widget_id)
+    }
+
+    /// Advances all running animations, applying their current values to their widgets, and
+    /// starts any animations that are ready to run.  Completion callbacks are called after the
+    /// final value has been applied.
+    pub fn update(&mut self, widget_store: &mut WidgetStore) {
+        let now = self.clock.now();
+        let mut completed: Vec<AnimationId> = Vec::new();
+
+        // Animations for widgets that do not exist, or have been removed, are dropped without
+        // calling their completion callbacks.
+        self.animations
+            .retain(|x| widget_store.is_existing_widget(x.widget_id));
+
+        for animation in self.animations.iter_mut() {
+            let started_at = match animation.started_at {
+                Some(started_at) => started_at,
+                None => continue,
+            };
+            let widget = &mut widget_store.widgets[animation.widget_id as usize].widget;
+            let elapsed = now.saturating_sub(started_at);
+
+            if elapsed >= animation.duration {
+                animation.track.apply(widget.as_mut(), 1.0);
+                completed.push(animation.id);
+            } else {
+                let progress = animation
+                    .easing
+                    .apply(elapsed as f64 / animation.duration as f64);
+
+                animation.track.apply(widget.as_mut(), progress);
+            }
+        }
+
+        for id in completed.iter() {
+            let position = self.animations.iter().position(|x| x.id == *id).unwrap();
+            let animation = self.animations.remove(position);
+
+            if let Some(callback) = animation.on_complete {
+                callback(animation.widget_id);
+            }
+        }
+
+        // Start any animations that are not waiting on another animation.  Starting an animation
+        // replaces any other animation running for the same widget and key.
+        let mut started: Vec<(AnimationId, i32, ConfigKeyId)> = Vec::new();
+
+        for position in 0..self.animations.len() {
+            let ready = {
+                let animation = &self.animations[position];
+
+                animation.started_at.is_none()
+                    && match animation.after {
+                        Some(after) => !self.animations.iter().any(|x| x.id == after),
+                        None => true,
+                    }
+            };
+
+            if ready {
+                let animation = &mut self.animations[position];
+                let widget = &mut widget_store.widgets[animation.widget_id as usize].widget;
+
+                animation.started_at = Some(now);
+                animation.track.start(widget.as_mut());
+                started.push((animation.id, animation.widget_id, animation.track.key_id()));
+            }
+        }
+
+        for (id, widget_id, key_id) in started.into_iter().rev() {
+            if !self.animations.iter().any(|x| x.id == id) {
+                continue;
+            }
+
+            self.animations.retain(|x| {
+                x.id == id
+                    || x.started_at.is_none()
+                    || x.widget_id != widget_id
+                    || x.track.key_id() != key_id
+            });
+        }
+    }
+}
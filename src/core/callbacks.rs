@@ -33,8 +33,13 @@ pub const CALLBACK_MOUSE_SCROLLED: u32 = 3;
 /// ```CallbackTypes::PointCallback``` callback.
 pub const CALLBACK_MOUSE_MOVED: u32 = 4;
 
+/// Index for timer fired callback, used by `TimerWidget` internally.  Refers to a
+/// ```CallbackTypes::CountCallback``` callback.
+pub const CALLBACK_TIMER_FIRED: u32 = 5;
+
 pub type SingleCallback = Box<Fn(i32) -> ()>;
 pub type PointCallback = Box<Fn(i32, Point) -> ()>;
+pub type CountCallback = Box<Fn(i32, u32) -> ()>;
 
 /// This is an enumerated type that is used to store numerous variations of callbacks that can
 /// be used within the `Widget` system.  This is written such that the `CallbackTypes` enum
@@ -45,6 +50,10 @@ pub enum CallbackTypes {
 
     /// Callback that supplies its widget ID and a `Point` on the screen within the `Widget`.
     PointCallback { callback: PointCallback },
+
+    /// Callback that supplies its widget ID and a count, such as the number of times a timer
+    /// has fired.
+    CountCallback { callback: CountCallback },
 }
 
 /// This is the `CallbackStore` that is used to store a list of `CallbackTypes` that are
@@ -0,0 +1,101 @@
+// Clock
+// Time sources used by timers and other time-based functionality
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::Cell;
+use std::time::Instant;
+
+/// Implementable trait that is used as a source of time.  Anything that measures elapsed time,
+/// such as the `Scheduler` and `TimerWidget`, reads the time from a `Clock`, so that tests can
+/// substitute a `ManualClock` and control exactly when time passes.
+pub trait Clock {
+    /// Returns the current time in milliseconds.  The starting point is up to the
+    /// implementation, but the value must never decrease.
+    fn now(&self) -> u64;
+}
+
+/// This is the `RealClock`, which measures time using the operating system's monotonic clock.
+/// Time starts at 0 when the clock is created.
+pub struct RealClock {
+    started: Instant,
+}
+
+/// Implementation of the `RealClock`.
+impl RealClock {
+    /// Creates a new `RealClock`, starting at 0 milliseconds.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Implementation of the `Clock` trait for the `RealClock`.
+impl Clock for RealClock {
+    fn now(&self) -> u64 {
+        let elapsed = self.started.elapsed();
+
+        (elapsed.as_secs() * 1_000) + u64::from(elapsed.subsec_millis())
+    }
+}
+
+/// This is the `ManualClock`, which only moves forward when it is told to.  Use this in tests
+/// to trigger time-based callbacks deterministically, without sleeping.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::clock::*;
+/// # fn main() {
+///     let clock = ManualClock::new();
+///
+///     assert_eq!(clock.now(), 0);
+///
+///     clock.advance(250);
+///     assert_eq!(clock.now(), 250);
+///
+///     clock.set(1000);
+///     assert_eq!(clock.now(), 1000);
+/// # }
+/// ```
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+
+/// Implementation of the `ManualClock`.
+impl ManualClock {
+    /// Creates a new `ManualClock`, starting at 0 milliseconds.
+    pub fn new() -> Self {
+        Self { now: Cell::new(0) }
+    }
+
+    /// Moves the clock forward by the specified number of milliseconds.
+    pub fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms);
+    }
+
+    /// Sets the clock to the specified time in milliseconds.  Time must not move backward, so
+    /// values earlier than the current time are ignored.
+    pub fn set(&self, ms: u64) {
+        if ms > self.now.get() {
+            self.now.set(ms);
+        }
+    }
+}
+
+/// Implementation of the `Clock` trait for the `ManualClock`.
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
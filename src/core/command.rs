@@ -0,0 +1,658 @@
+// Commands
+// Reversible commands and an undo/redo history for changes to a WidgetStore.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::types::Color;
+use std::any::Any;
+use std::collections::VecDeque;
+
+use crate::core::point::*;
+use crate::core::widget_store::*;
+use crate::widget::config::*;
+use crate::widget::widget::*;
+
+/// A reversible change to a `WidgetStore`, recorded by a `CommandHistory` so that it can be
+/// undone and redone.
+pub trait Command: AsAny {
+    /// Applies the change.  Called when the command is executed, and when it is redone.
+    fn apply(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError>;
+
+    /// Reverts the change made by `apply`.
+    fn revert(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError>;
+
+    /// Merges a command that has just been applied into this command, so that both are undone
+    /// in one step.  Returns `true` if the command was merged, `false` if it should be recorded
+    /// separately.  Only called between `CommandHistory::begin_merge` and `end_merge`, on the
+    /// most recent command of the step.
+    fn merge(&mut self, _next: &dyn Command) -> bool {
+        false
+    }
+}
+
+/// A `Command` that sets a configuration value of a widget.  Undoing it restores the value
+/// that was set on the widget before, or removes the value if none was set.  Consecutive
+/// commands setting the same key of the same widget are merged.
+///
+/// The name is set with `WidgetStore::set_widget_name`, so a name that is already used is
+/// reported as a `WidgetStoreError::DuplicateName` error.  The origin, size, color, visibility
+/// and enabled state are set through the widget's setters, so that widgets that override them
+/// see the new values.  These keys, and the name, are set back to their default value when the
+/// command is undone if no value was set before.  Returns a `WidgetStoreError::InvalidWidget`
+/// error if the widget does not exist, or has been removed.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::command::*;
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::timer_widget::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let mut history = CommandHistory::new();
+///     let ok_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+///     let other_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+///
+///     history.set(&mut widget_store, ok_id, CONFIG_NAME, "ok".to_string()).unwrap();
+///     assert_eq!(history.set(&mut widget_store, other_id, CONFIG_NAME, "ok".to_string()),
+///         Err(WidgetStoreError::DuplicateName("ok".to_string())));
+///
+///     widget_store.remove_widget(other_id);
+///     assert_eq!(history.set(&mut widget_store, other_id, CONFIG_VISIBLE, false),
+///         Err(WidgetStoreError::InvalidWidget(other_id)));
+///     assert_eq!(
+///         history.execute(&mut widget_store, Box::new(SetParentCommand::new(other_id, ok_id))),
+///         Err(WidgetStoreError::InvalidWidget(other_id)));
+///
+///     // A limit of 0 keeps no steps, even while merging.
+///     history.set_limit(0);
+///     history.begin_merge();
+///     history.set(&mut widget_store, ok_id, CONFIG_VISIBLE, false).unwrap();
+///     history.set(&mut widget_store, ok_id, CONFIG_VISIBLE, true).unwrap();
+///     history.end_merge();
+///
+///     assert!(!history.can_undo());
+///     assert_eq!(widget_store.find_by_name("ok"), Some(ok_id));
+///
+///     // Undoing a value that was never set reverts it to its default.
+///     history.set_limit(10);
+///
+///     let timer_id = widget_store.add_widget(Box::new(TimerWidget::new())).unwrap();
+///
+///     history.set(&mut widget_store, timer_id, CONFIG_NAME, "timer".to_string()).unwrap();
+///     history.set(&mut widget_store, timer_id, CONFIG_ENABLED, false).unwrap();
+///     history.undo(&mut widget_store).unwrap();
+///     history.undo(&mut widget_store).unwrap();
+///
+///     assert!(widget_store.get_as::<TimerWidget>(timer_id).unwrap().is_running());
+///     assert_eq!(widget_store.find_by_name("timer"), None);
+/// # }
+/// ```
+pub struct SetConfigCommand<T> {
+    widget_id: i32,
+    key: ConfigKey<T>,
+    old_value: Option<T>,
+    new_value: T,
+}
+
+/// Implementation of the `SetConfigCommand`.
+impl<T: Clone + 'static> SetConfigCommand<T> {
+    /// Creates a new command that sets the value for `key` of the widget specified by its ID.
+    pub fn new(widget_id: i32, key: ConfigKey<T>, value: T) -> Self {
+        Self {
+            widget_id,
+            key,
+            old_value: None,
+            new_value: value,
+        }
+    }
+
+    fn set_value(
+        &self,
+        widget_store: &mut WidgetStore,
+        value: Option<T>,
+    ) -> Result<(), WidgetStoreError> {
+        if !widget_store.is_existing_widget(self.widget_id) {
+            return Err(WidgetStoreError::InvalidWidget(self.widget_id));
+        }
+
+        // The keys that are set through the store or the widget's setters are set back to their
+        // default value through the same path, so that widgets see the change.
+        let key_id = self.key.id();
+        let value = match value {
+            Some(value) => value,
+            None if [
+                CONFIG_NAME.id(),
+                CONFIG_ORIGIN.id(),
+                CONFIG_SIZE.id(),
+                CONFIG_COLOR.id(),
+                CONFIG_VISIBLE.id(),
+                CONFIG_ENABLED.id(),
+            ]
+            .contains(&key_id) =>
+            {
+                self.key.default_value()
+            }
+            None => {
+                if let Some(widget) = widget_store.get_widget_mut(self.widget_id) {
+                    remove_widget_config(widget, self.key);
+                }
+
+                return Ok(());
+            }
+        };
+
+        // The key IDs include the value type, so the downcasts always succeed.
+        let any_value: &dyn Any = &value;
+
+        if key_id == CONFIG_NAME.id() {
+            if let Some(name) = any_value.downcast_ref::<String>() {
+                return widget_store.set_widget_name(self.widget_id, name);
+            }
+        }
+
+        let widget = match widget_store.get_widget_mut(self.widget_id) {
+            Some(widget) => widget,
+            None => return Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        };
+
+        if key_id == CONFIG_ORIGIN.id() {
+            if let Some(origin) = any_value.downcast_ref::<Point>() {
+                widget.set_origin(origin.x, origin.y);
+                return Ok(());
+            }
+        } else if key_id == CONFIG_SIZE.id() {
+            if let Some(size) = any_value.downcast_ref::<Size>() {
+                widget.set_size(size.w, size.h);
+                return Ok(());
+            }
+        } else if key_id == CONFIG_COLOR.id() {
+            if let Some(color) = any_value.downcast_ref::<Color>() {
+                widget.set_color(*color);
+                return Ok(());
+            }
+        } else if key_id == CONFIG_VISIBLE.id() {
+            if let Some(visible) = any_value.downcast_ref::<bool>() {
+                widget.set_visible(*visible);
+                return Ok(());
+            }
+        } else if key_id == CONFIG_ENABLED.id() {
+            if let Some(enabled) = any_value.downcast_ref::<bool>() {
+                widget.set_enabled(*enabled);
+                return Ok(());
+            }
+        }
+
+        set_widget_config(widget, self.key, value);
+        Ok(())
+    }
+}
+
+impl<T: Clone + 'static> Command for SetConfigCommand<T> {
+    fn apply(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        let config = match widget_store.get_widget_mut(self.widget_id) {
+            Some(widget) => widget.config(),
+            None => return Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        };
+
+        self.old_value = if config.contains_key(self.key) {
+            Some(config.get(self.key))
+        } else {
+            None
+        };
+
+        self.set_value(widget_store, Some(self.new_value.clone()))
+    }
+
+    fn revert(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        self.set_value(widget_store, self.old_value.clone())
+    }
+
+    fn merge(&mut self, next: &dyn Command) -> bool {
+        match (*next).as_any().downcast_ref::<Self>() {
+            Some(next) if next.widget_id == self.widget_id && next.key.id() == self.key.id() => {
+                self.new_value = next.new_value.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A `Command` that adds a widget to a parent.  Undoing it removes the widget along with its
+/// descendants, and redoing it restores them with the same IDs.  Returns a
+/// `WidgetStoreError::InvalidParent` error if the parent does not exist, or a
+/// `WidgetStoreError::DuplicateName` error if the widget's name is already used, in which case
+/// the widget is kept so that the command can be applied again.
+pub struct AddWidgetCommand {
+    parent_id: i32,
+    widget: Option<Box<dyn Widget>>,
+    widget_id: i32,
+    detached: Option<DetachedWidget>,
+}
+
+/// Implementation of the `AddWidgetCommand`.
+impl AddWidgetCommand {
+    /// Creates a new command that adds the widget to the parent specified by its ID.
+    pub fn new(widget: Box<dyn Widget>, parent_id: i32) -> Self {
+        Self {
+            parent_id,
+            widget: Some(widget),
+            widget_id: -1,
+            detached: None,
+        }
+    }
+
+    /// Retrieves the ID of the widget once the command has been applied, or -1 before.
+    pub fn get_widget_id(&self) -> i32 {
+        self.widget_id
+    }
+}
+
+impl Command for AddWidgetCommand {
+    fn apply(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        if let Some(detached) = self.detached.take() {
+            return restore_detached(widget_store, detached, &mut self.detached);
+        }
+
+        let widget = match self.widget.as_mut() {
+            Some(widget) => widget,
+            None => return Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        };
+
+        // The widget is only taken once it is known that it can be added.
+        if !widget_store.is_existing_widget(self.parent_id) {
+            return Err(WidgetStoreError::InvalidParent(self.parent_id));
+        }
+
+        if let Some(name) = widget.get_name() {
+            if widget_store.find_by_name(&name).is_some() {
+                return Err(WidgetStoreError::DuplicateName(name));
+            }
+        }
+
+        if let Some(widget) = self.widget.take() {
+            self.widget_id = widget_store.add_widget_to_parent(widget, self.parent_id)?;
+        }
+
+        Ok(())
+    }
+
+    fn revert(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        match widget_store.take_widget(self.widget_id) {
+            Some(detached) => {
+                self.detached = Some(detached);
+                Ok(())
+            }
+            None => Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        }
+    }
+}
+
+/// A `Command` that removes a widget along with its descendants.  Undoing it restores them
+/// with the same IDs.  Returns a `WidgetStoreError::InvalidWidget` error if the widget does not
+/// exist, or is the base widget.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::command::*;
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let mut history = CommandHistory::with_limit(2);
+///     let panel_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+///     let child_id = widget_store
+///         .add_widget_to_parent(Box::new(BaseWidget::new()), panel_id)
+///         .unwrap();
+///
+///     history.execute(&mut widget_store, Box::new(SetParentCommand::new(child_id, 0))).unwrap();
+///     history.execute(&mut widget_store, Box::new(RemoveWidgetCommand::new(panel_id))).unwrap();
+///     history.execute(&mut widget_store, Box::new(RemoveWidgetCommand::new(child_id))).unwrap();
+///     assert!(widget_store.get_descendants_of(0).is_empty());
+///
+///     // Only the last two steps are kept.
+///     while history.undo(&mut widget_store).unwrap() {}
+///
+///     assert_eq!(widget_store.get_children_of(0), vec![0, panel_id, child_id]);
+/// # }
+/// ```
+pub struct RemoveWidgetCommand {
+    widget_id: i32,
+    detached: Option<DetachedWidget>,
+}
+
+/// Implementation of the `RemoveWidgetCommand`.
+impl RemoveWidgetCommand {
+    /// Creates a new command that removes the widget specified by its ID.
+    pub fn new(widget_id: i32) -> Self {
+        Self {
+            widget_id,
+            detached: None,
+        }
+    }
+}
+
+impl Command for RemoveWidgetCommand {
+    fn apply(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        self.detached = widget_store.take_widget(self.widget_id);
+
+        match self.detached {
+            Some(_) => Ok(()),
+            None => Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        }
+    }
+
+    fn revert(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        match self.detached.take() {
+            Some(detached) => restore_detached(widget_store, detached, &mut self.detached),
+            None => Err(WidgetStoreError::InvalidWidget(self.widget_id)),
+        }
+    }
+}
+
+/// Restores widgets removed by a command.  If they cannot be restored, they are put back in
+/// `slot`, so that the command can be retried.
+fn restore_detached(
+    widget_store: &mut WidgetStore,
+    mut detached: DetachedWidget,
+    slot: &mut Option<DetachedWidget>,
+) -> Result<(), WidgetStoreError> {
+    if let Err(error) = widget_store.check_restore_widget(&mut detached) {
+        *slot = Some(detached);
+        return Err(error);
+    }
+
+    widget_store.restore_widget(detached)
+}
+
+/// A `Command` that moves a widget to another parent with `WidgetStore::set_parent`.  Undoing
+/// it moves the widget back to its previous parent.  Returns a
+/// `WidgetStoreError::InvalidWidget` error if the widget does not exist, or has been removed.
+pub struct SetParentCommand {
+    widget_id: i32,
+    parent_id: i32,
+    old_parent_id: i32,
+}
+
+/// Implementation of the `SetParentCommand`.
+impl SetParentCommand {
+    /// Creates a new command that moves the widget specified by its ID to a new parent.
+    pub fn new(widget_id: i32, parent_id: i32) -> Self {
+        Self {
+            widget_id,
+            parent_id,
+            old_parent_id: -1,
+        }
+    }
+}
+
+impl Command for SetParentCommand {
+    fn apply(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        if !widget_store.is_existing_widget(self.widget_id) {
+            return Err(WidgetStoreError::InvalidWidget(self.widget_id));
+        }
+
+        self.old_parent_id = widget_store.get_parent_of(self.widget_id);
+        widget_store.set_parent(self.widget_id, self.parent_id)
+    }
+
+    fn revert(&mut self, widget_store: &mut WidgetStore) -> Result<(), WidgetStoreError> {
+        widget_store.set_parent(self.widget_id, self.old_parent_id)
+    }
+}
+
+/// This is the `CommandHistory`, which executes `Command` objects against a `WidgetStore`, and
+/// records them so that they can be undone and redone.  Executing a command clears the commands
+/// that can be redone.
+///
+/// Commands executed between `begin_merge` and `end_merge` are undone in one step, such as all
+/// of the changes made during a drag.  Within that step, a command is merged into the command
+/// before it if that command accepts it, so that moving a widget a hundred times records only
+/// its first and last origin.
+///
+/// The number of steps that can be undone is limited, 100 by default; older steps are
+/// discarded.  A limit of 0 keeps no steps.
+///
+/// Only the changes made through the history, with `execute`, `set` and `record`, can be
+/// undone.  Changes made directly to the `WidgetStore` or to a widget's configuration are not
+/// recorded, and are not reverted by `undo`.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::command::*;
+/// # use pushrod::core::point::*;
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let mut history = CommandHistory::new();
+///     let mut add_command = AddWidgetCommand::new(Box::new(BaseWidget::new()), 0);
+///
+///     // Commands can be applied directly, and then recorded.
+///     add_command.apply(&mut widget_store).unwrap();
+///
+///     let widget_id = add_command.get_widget_id();
+///
+///     history.record(Box::new(add_command));
+///
+///     // A drag is undone in one step.
+///     history.begin_merge();
+///
+///     for x in 1..=10 {
+///         history.set(&mut widget_store, widget_id, CONFIG_ORIGIN, Point { x: x * 10, y: 0 })
+///             .unwrap();
+///     }
+///
+///     history.end_merge();
+///
+///     assert_eq!(widget_store.get_widget_mut(widget_id).unwrap().get_origin().x, 100);
+///     assert!(history.undo(&mut widget_store).unwrap());
+///     assert_eq!(widget_store.get_widget_mut(widget_id).unwrap().get_origin().x, 0);
+///
+///     // Undoing the add removes the widget; redoing it restores the same ID.
+///     history.undo(&mut widget_store).unwrap();
+///     assert!(widget_store.get_descendants_of(0).is_empty());
+///     assert!(!history.can_undo());
+///
+///     history.redo(&mut widget_store).unwrap();
+///     history.redo(&mut widget_store).unwrap();
+///     assert_eq!(widget_store.get_descendants_of(0), vec![widget_id]);
+///     assert_eq!(widget_store.get_widget_mut(widget_id).unwrap().get_origin().x, 100);
+///     assert!(!history.can_redo());
+/// # }
+/// ```
+pub struct CommandHistory {
+    undo_steps: VecDeque<Vec<Box<dyn Command>>>,
+    redo_steps: Vec<Vec<Box<dyn Command>>>,
+    limit: usize,
+    merging: bool,
+    merge_step_open: bool,
+}
+
+/// Implementation of the `CommandHistory`.
+impl CommandHistory {
+    /// Creates a new, empty `CommandHistory`, which keeps up to 100 steps.
+    pub fn new() -> Self {
+        Self::with_limit(100)
+    }
+
+    /// Creates a new, empty `CommandHistory`, which keeps up to `limit` steps.
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            undo_steps: VecDeque::new(),
+            redo_steps: Vec::new(),
+            limit,
+            merging: false,
+            merge_step_open: false,
+        }
+    }
+
+    /// Sets the number of steps that can be undone, discarding the oldest steps if there are
+    /// more.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.apply_limit();
+    }
+
+    /// Retrieves the number of steps that can be undone.
+    pub fn get_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Applies a command, and records it so that it can be undone.  The command is not recorded
+    /// if it returns an error.
+    pub fn execute(
+        &mut self,
+        widget_store: &mut WidgetStore,
+        mut command: Box<dyn Command>,
+    ) -> Result<(), WidgetStoreError> {
+        command.apply(widget_store)?;
+        self.record(command);
+        Ok(())
+    }
+
+    /// Sets a configuration value of the widget specified by its ID, by executing a
+    /// `SetConfigCommand`.
+    pub fn set<T: Clone + 'static>(
+        &mut self,
+        widget_store: &mut WidgetStore,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        value: T,
+    ) -> Result<(), WidgetStoreError> {
+        self.execute(
+            widget_store,
+            Box::new(SetConfigCommand::new(widget_id, key, value)),
+        )
+    }
+
+    /// Records a command that has already been applied, so that it can be undone.  Clears the
+    /// commands that can be redone.
+    pub fn record(&mut self, command: Box<dyn Command>) {
+        self.redo_steps.clear();
+
+        let open_step = if self.merge_step_open {
+            self.undo_steps.back_mut()
+        } else {
+            None
+        };
+
+        if let Some(step) = open_step {
+            let merged = step.last_mut().map_or(false, |x| x.merge(command.as_ref()));
+
+            if !merged {
+                step.push(command);
+            }
+        } else {
+            self.undo_steps.push_back(vec![command]);
+            self.merge_step_open = self.merging;
+            self.apply_limit();
+        }
+    }
+
+    /// Starts merging commands: the commands executed until `end_merge` is called are undone in
+    /// one step.
+    pub fn begin_merge(&mut self) {
+        self.merging = true;
+        self.merge_step_open = false;
+    }
+
+    /// Stops merging commands.
+    pub fn end_merge(&mut self) {
+        self.merging = false;
+        self.merge_step_open = false;
+    }
+
+    /// Indicates whether or not there is a step that can be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_steps.is_empty()
+    }
+
+    /// Indicates whether or not there is a step that can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_steps.is_empty()
+    }
+
+    /// Undoes the most recent step, reverting its commands in reverse order.  Returns `false`
+    /// if there is nothing to undo.  If a command returns an error, the commands in the step
+    /// that were already reverted are applied again, and the step remains undoable.
+    pub fn undo(&mut self, widget_store: &mut WidgetStore) -> Result<bool, WidgetStoreError> {
+        let mut step = match self.undo_steps.pop_back() {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+
+        self.merge_step_open = false;
+
+        for i in (0..step.len()).rev() {
+            if let Err(error) = step[i].revert(widget_store) {
+                for command in step[i + 1..].iter_mut() {
+                    let _ = command.apply(widget_store);
+                }
+
+                self.undo_steps.push_back(step);
+                return Err(error);
+            }
+        }
+
+        self.redo_steps.push(step);
+        Ok(true)
+    }
+
+    /// Redoes the most recently undone step, applying its commands in order.  Returns `false`
+    /// if there is nothing to redo.  If a command returns an error, the commands in the step
+    /// that were already applied are reverted, and the step remains redoable.
+    pub fn redo(&mut self, widget_store: &mut WidgetStore) -> Result<bool, WidgetStoreError> {
+        let mut step = match self.redo_steps.pop() {
+            Some(step) => step,
+            None => return Ok(false),
+        };
+
+        self.merge_step_open = false;
+
+        for i in 0..step.len() {
+            if let Err(error) = step[i].apply(widget_store) {
+                for command in step[..i].iter_mut().rev() {
+                    let _ = command.revert(widget_store);
+                }
+
+                self.redo_steps.push(step);
+                return Err(error);
+            }
+        }
+
+        self.undo_steps.push_back(step);
+        Ok(true)
+    }
+
+    /// Discards all of the recorded steps.
+    pub fn clear(&mut self) {
+        self.undo_steps.clear();
+        self.redo_steps.clear();
+        self.merge_step_open = false;
+    }
+
+    fn apply_limit(&mut self) {
+        while self.undo_steps.len() > self.limit {
+            self.undo_steps.pop_front();
+        }
+
+        // The step being merged into may have been discarded.
+        if self.undo_steps.is_empty() {
+            self.merge_step_open = false;
+        }
+    }
+}
@@ -0,0 +1,411 @@
+// Hot Reload
+// Reloads layout and style sheet files when they change on disk.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::core::widget_store::*;
+use crate::widget::layout::*;
+use crate::widget::registry::*;
+use crate::widget::stylesheet::*;
+use crate::widget::theme::*;
+use crate::widget::widget::*;
+
+/// Closure called with a widget loaded from a watched layout file, used to attach callbacks
+/// to the widget by its name.
+pub type AttachCallback = Box<dyn Fn(&mut dyn Widget)>;
+
+/// A watched file, along with its modification time when it was last loaded, and the error
+/// that occurred when it was last loaded, if any.
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    error: Option<String>,
+}
+
+impl WatchedFile {
+    fn new(path: &Path) -> Self {
+        let mut file = Self {
+            path: path.to_path_buf(),
+            modified: None,
+            error: None,
+        };
+
+        file.has_changed();
+        file
+    }
+
+    /// Indicates whether or not the file has been modified since this was last called.
+    fn has_changed(&mut self) -> bool {
+        let modified = fs::metadata(&self.path).and_then(|x| x.modified()).ok();
+
+        if modified != self.modified {
+            self.modified = modified;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A watched layout file, along with the IDs of the top-level widgets it added.
+struct WatchedLayout {
+    file: WatchedFile,
+    registry: WidgetRegistry,
+    root_ids: Vec<i32>,
+}
+
+/// This is the `HotReloader`, which watches layout and style sheet files, and reloads them
+/// into a `WidgetStore` when they change on disk.  `Pushrod` checks its files during the update
+/// phase of the run loop, using `Pushrod::watch_layout`, `watch_stylesheet` and `attach`.
+///
+/// When a layout file changes, the widgets in the new layout are added, and the widgets that
+/// the file added before are removed once all of the new widgets have been added, so that they
+/// are kept if the new layout cannot be added.  Widget IDs change, but names stay the same, so
+/// callbacks should be attached by name with `attach`, which calls its closure every time the
+/// named widget is loaded.  When a style sheet changes, the theme is rebuilt from all of the
+/// watched style sheets, in the order they were watched, replacing the theme of the
+/// `WidgetStore`.
+///
+/// If a file cannot be loaded, the widgets and theme that were previously loaded are kept, and
+/// the error is available from `get_error` until the file is fixed.  `Pushrod` draws the error
+/// over the window instead of stopping.
+///
+/// Reloading a layout replaces its widgets with new widgets, which take the place of the old
+/// widgets among the children of the base widget, so they are drawn in the same order.  The new
+/// widgets have new IDs, however: bindings, animations and callbacks that refer to the old IDs
+/// are not moved to the new widgets, and the timers of the old widgets are cancelled.  Use
+/// `attach` to set these up again by name after every reload.  The IDs of removed widgets are
+/// not reused, so every reload leaves the placeholders of the old widgets in the store.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::hot_reload::*;
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::registry::*;
+/// # use pushrod::widget::widget::*;
+/// # use std::fs;
+/// # fn main() {
+///     let path = std::env::temp_dir().join("pushrod-hot-reload-example.json");
+///     let mut widget_store = WidgetStore::new();
+///     let mut hot_reloader = HotReloader::new();
+///
+///     fs::write(&path, r#"{ "widgets": [ { "type": "box", "name": "ok", "origin": [10, 10] } ] }"#)
+///         .unwrap();
+///     hot_reloader.watch_layout(&mut widget_store, &path, WidgetRegistry::new()).unwrap();
+///     hot_reloader.attach(&mut widget_store, "ok", Box::new(|widget| widget.add_class("attached")));
+///
+///     let other_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+///
+///     fs::write(&path, r#"{ "widgets": [ { "type": "box", "name": "ok", "origin": [20, 10] } ] }"#)
+///         .unwrap();
+///     hot_reloader.reload_all(&mut widget_store);
+///
+///     let ok_id = widget_store.find_by_name("ok").unwrap();
+///
+///     assert_eq!(widget_store.get_widget_mut(ok_id).unwrap().get_origin().x, 20);
+///     assert!(widget_store.get_widget_mut(ok_id).unwrap().has_class("attached"));
+///     assert_eq!(widget_store.get_children_of(0), vec![0, ok_id, other_id]);
+///
+///     // A broken layout keeps the widgets that were loaded, and reports the error.
+///     fs::write(&path, r#"{ "widgets": [ { "type": "box", "origin": [20, 10 } ] }"#).unwrap();
+///     hot_reloader.reload_all(&mut widget_store);
+///
+///     assert!(hot_reloader.get_error().unwrap().ends_with("1:51: Expected ',' or ']', found '}'"));
+///     assert_eq!(widget_store.find_by_name("ok"), Some(ok_id));
+///
+///     // So does a layout that uses a font that is not in the assets folder.
+///     fs::write(&path, r#"{ "widgets": [ { "type": "box", "font-name": "missing.ttf" } ] }"#)
+///         .unwrap();
+///     hot_reloader.reload_all(&mut widget_store);
+///
+///     assert!(hot_reloader.get_error().unwrap()
+///         .ends_with("widgets[0].font-name: Font 'missing.ttf' not found in the assets folder"));
+///     assert_eq!(widget_store.find_by_name("ok"), Some(ok_id));
+/// #   fs::remove_file(&path).unwrap();
+/// # }
+/// ```
+pub struct HotReloader {
+    layouts: Vec<WatchedLayout>,
+    stylesheets: Vec<WatchedFile>,
+    attachments: Vec<(String, AttachCallback)>,
+}
+
+/// Implementation of the `HotReloader`.
+impl HotReloader {
+    /// Creates a new `HotReloader` that does not watch any files.
+    pub fn new() -> Self {
+        Self {
+            layouts: Vec::new(),
+            stylesheets: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Loads a layout file into the `WidgetStore` with `load_layout`, creating its widgets with
+    /// the specified `WidgetRegistry`, and reloads it whenever the file changes.  Returns the IDs
+    /// of the named widgets in the layout.  The file is watched even if it cannot be loaded, so
+    /// that it is loaded once it has been fixed.
+    pub fn watch_layout<P: AsRef<Path>>(
+        &mut self,
+        widget_store: &mut WidgetStore,
+        path: P,
+        registry: WidgetRegistry,
+    ) -> Result<HashMap<String, i32>, LayoutError> {
+        self.layouts.push(WatchedLayout {
+            file: WatchedFile::new(path.as_ref()),
+            registry,
+            root_ids: Vec::new(),
+        });
+
+        self.reload_layout(widget_store, self.layouts.len() - 1)
+    }
+
+    /// Loads a style sheet file into the theme of the `WidgetStore`, and reloads it whenever the
+    /// file changes.  The theme is rebuilt from all of the watched style sheets, replacing any
+    /// theme set with `WidgetStore::set_theme`.  The file is watched even if it cannot be loaded.
+    pub fn watch_stylesheet<P: AsRef<Path>>(
+        &mut self,
+        widget_store: &mut WidgetStore,
+        path: P,
+    ) -> Result<(), StylesheetError> {
+        self.stylesheets.push(WatchedFile::new(path.as_ref()));
+        self.reload_stylesheets(widget_store)
+    }
+
+    /// Registers a closure that is called with the widget of the specified name every time it
+    /// is loaded from a watched layout, so that callbacks are attached again after a reload.
+    /// The closure is called immediately if the widget already exists.
+    pub fn attach(&mut self, widget_store: &mut WidgetStore, name: &str, callback: AttachCallback) {
+        if let Some(widget_id) = widget_store.find_by_name(name) {
+            if let Some(widget) = widget_store.get_widget_mut(widget_id) {
+                callback(widget);
+            }
+        }
+
+        self.attachments.push((name.to_string(), callback));
+    }
+
+    /// Reloads the watched files that have changed on disk since they were last loaded.  Returns
+    /// `true` if any file was reloaded.
+    pub fn update(&mut self, widget_store: &mut WidgetStore) -> bool {
+        let mut reloaded = false;
+
+        for index in 0..self.layouts.len() {
+            if self.layouts[index].file.has_changed() {
+                let _ = self.reload_layout(widget_store, index);
+                reloaded = true;
+            }
+        }
+
+        let stylesheets_changed = self
+            .stylesheets
+            .iter_mut()
+            .map(WatchedFile::has_changed)
+            .fold(false, |changed, x| changed || x);
+
+        if stylesheets_changed {
+            let _ = self.reload_stylesheets(widget_store);
+            reloaded = true;
+        }
+
+        reloaded
+    }
+
+    /// Reloads all of the watched files, whether or not they have changed.
+    pub fn reload_all(&mut self, widget_store: &mut WidgetStore) {
+        for index in 0..self.layouts.len() {
+            let _ = self.reload_layout(widget_store, index);
+        }
+
+        if !self.stylesheets.is_empty() {
+            let _ = self.reload_stylesheets(widget_store);
+        }
+    }
+
+    /// Retrieves the error that occurred when a watched file was last loaded, prefixed by the
+    /// path of the file.  Returns `None` if all of the watched files were loaded.
+    pub fn get_error(&self) -> Option<&str> {
+        self.layouts
+            .iter()
+            .map(|x| &x.file)
+            .chain(self.stylesheets.iter())
+            .filter_map(|x| x.error.as_ref())
+            .map(String::as_str)
+            .next()
+    }
+
+    /// Replaces the widgets added by the layout at `index` with the widgets in its file.
+    fn reload_layout(
+        &mut self,
+        widget_store: &mut WidgetStore,
+        index: usize,
+    ) -> Result<HashMap<String, i32>, LayoutError> {
+        let layout = &mut self.layouts[index];
+        let result = fs::read_to_string(&layout.file.path)
+            .map_err(LayoutError::from)
+            .and_then(|source| ParsedLayout::parse(&layout.registry, &source))
+            .and_then(|parsed| {
+                let old_root_ids: Vec<i32> = layout
+                    .root_ids
+                    .iter()
+                    .cloned()
+                    .filter(|x| widget_store.is_existing_widget(*x))
+                    .collect();
+                let mut replaced_ids = old_root_ids.clone();
+
+                for root_id in &old_root_ids {
+                    replaced_ids.extend(widget_store.get_descendants_of(*root_id));
+                }
+
+                parsed.check_names(widget_store, &replaced_ids)?;
+
+                // The old widgets give up their names while the new widgets are added, so that
+                // they can be given back if the new widgets cannot be added.
+                let mut old_names = Vec::new();
+
+                for widget_id in replaced_ids {
+                    let name = widget_store
+                        .get_widget_mut(widget_id)
+                        .and_then(|x| x.get_name());
+
+                    if let Some(name) = name {
+                        let _ = widget_store.set_widget_name(widget_id, "");
+                        old_names.push((widget_id, name));
+                    }
+                }
+
+                let first_id = widget_store.widgets.len() as i32;
+                let old_order = old_root_ids
+                    .first()
+                    .map(|x| widget_store.get_sibling_order(*x));
+
+                match parsed.add_to(widget_store) {
+                    Ok(ids) => {
+                        for root_id in &old_root_ids {
+                            widget_store.remove_widget(*root_id);
+                        }
+
+                        layout.root_ids = widget_store
+                            .get_children_of(0)
+                            .into_iter()
+                            .filter(|x| *x >= first_id)
+                            .collect();
+
+                        // The new widgets take the place of the old widgets among the children
+                        // of the base widget, rather than being drawn over the widgets added
+                        // after them.
+                        if let Some(order) = old_order {
+                            for root_id in &layout.root_ids {
+                                widget_store.set_sibling_order(*root_id, order);
+                            }
+                        }
+
+                        Ok(ids)
+                    }
+                    Err(error) => {
+                        // The widgets that were added have been removed again, so the names
+                        // are free.
+                        for (widget_id, name) in old_names {
+                            let _ = widget_store.set_widget_name(widget_id, &name);
+                        }
+
+                        Err(error)
+                    }
+                }
+            });
+
+        layout.file.error = result
+            .as_ref()
+            .err()
+            .map(|error| format!("{}: {}", layout.file.path.display(), error));
+
+        if let Ok(ids) = &result {
+            for (name, callback) in &self.attachments {
+                if let Some(widget) = ids.get(name).and_then(|x| widget_store.get_widget_mut(*x)) {
+                    callback(widget);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Rebuilds the theme from the watched style sheets, keeping the current theme if any of
+    /// them cannot be loaded.
+    fn reload_stylesheets(
+        &mut self,
+        widget_store: &mut WidgetStore,
+    ) -> Result<(), StylesheetError> {
+        let mut theme = Theme::new();
+
+        for file in self.stylesheets.iter_mut() {
+            file.error = None;
+        }
+
+        for file in self.stylesheets.iter_mut() {
+            if let Err(error) = load_stylesheet(&mut theme, &file.path) {
+                file.error = Some(format!("{}: {}", file.path.display(), error));
+                return Err(error);
+            }
+        }
+
+        widget_store.set_theme(theme);
+        Ok(())
+    }
+}
+
+/// Draws an error message over the top of the window, one line of text for each line of the
+/// message, on a red background.  Only the background is drawn if there is no font to draw
+/// the message with.
+pub(crate) fn draw_error_overlay(
+    message: &str,
+    width: f64,
+    font: Option<&mut Glyphs>,
+    c: Context,
+    g: &mut G2d,
+) {
+    let lines: Vec<&str> = message.lines().collect();
+
+    rectangle(
+        [0.75, 0.0, 0.0, 0.9],
+        [0.0, 0.0, width, lines.len() as f64 * 20.0 + 12.0],
+        c.transform,
+        g,
+    );
+
+    let font = match font {
+        Some(font) => font,
+        None => return,
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let drawn = text(
+            [1.0; 4],
+            14,
+            line,
+            font,
+            c.transform.trans(8.0, i as f64 * 20.0 + 22.0),
+            g,
+        );
+
+        if drawn.is_err() {
+            break;
+        }
+    }
+}
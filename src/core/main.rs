@@ -13,10 +13,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::animation::*;
+use crate::core::callbacks::*;
+use crate::core::clock::*;
+use crate::core::hot_reload::*;
 use crate::core::point::*;
+use crate::core::scheduler::*;
 use crate::core::widget_store::*;
+use crate::widget::config::*;
+use crate::widget::layout::*;
+use crate::widget::registry::*;
+use crate::widget::stylesheet::*;
+use crate::widget::text_widget::*;
+use crate::widget::theme::*;
 
 use piston_window::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 
 /// This structure is returned when instantiating a new Pushrod main object.
 /// It stores the OpenGL configuration that is desired for drawing, a list of references
@@ -28,6 +43,11 @@ use piston_window::*;
 pub struct Pushrod {
     window: PistonWindow,
     pub widget_store: WidgetStore,
+    clock: Rc<dyn Clock>,
+    scheduler: SharedScheduler,
+    animator: Animator,
+    hot_reloader: HotReloader,
+    error_font: Option<Result<Glyphs, String>>,
 //    event_listeners: RefCell<Vec<Box<EventListener>>>,
 //    event_list: RefCell<Vec<PushrodEvent>>,
 }
@@ -54,9 +74,24 @@ pub struct Pushrod {
 impl Pushrod {
     /// Pushrod Object Constructor.  Takes in a single OpenGL configuration type.
     pub fn new(window: PistonWindow) -> Self {
+        Self::with_clock(window, Rc::new(RealClock::new()))
+    }
+
+    /// Pushrod Object Constructor, which reads time from the specified `Clock`.  The clock is
+    /// shared by the scheduler and the animator, so a `ManualClock` can be used to control
+    /// exactly when timers fire and animations advance.  The scheduler is shared with the
+    /// `WidgetStore`, so `TimerWidget`s added to it follow the same clock.
+    pub fn with_clock(window: PistonWindow, clock: Rc<dyn Clock>) -> Self {
+        let scheduler = Rc::new(RefCell::new(Scheduler::with_clock(clock.clone())));
+
         Self {
             window,
-            widget_store: WidgetStore::new(),
+            widget_store: WidgetStore::with_scheduler(scheduler.clone()),
+            clock: clock.clone(),
+            scheduler,
+            animator: Animator::with_clock(clock),
+            hot_reloader: HotReloader::new(),
+            error_font: None,
 //            event_listeners: RefCell::new(Vec::new()),
 //            event_list: RefCell::new(Vec::new()),
         }
@@ -159,10 +194,211 @@ impl Pushrod {
 //        0
 //    }
 
+    /// Schedules a closure to be called once, after `timeout` milliseconds have elapsed.  The
+    /// closure is called during the update phase of the run loop, independent of the screen
+    /// refresh rate.  Returns the `TimerId` that can be used to `cancel` the timeout.
+    pub fn set_timeout(&mut self, timeout: u64, callback: TimerCallback) -> TimerId {
+        self.scheduler.borrow_mut().set_timeout(timeout, callback)
+    }
+
+    /// Schedules a closure to be called every `interval` milliseconds until it is cancelled.
+    /// Returns the `TimerId` that can be used to `cancel` the interval.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use piston_window::*;
+    /// # use pushrod::core::main::*;
+    /// # fn main() {
+    ///     let mut prod: Pushrod = Pushrod::new(WindowSettings::new("Pushrod Window", [640, 480])
+    ///             .opengl(OpenGL::V3_2)
+    ///             .build()
+    ///             .unwrap_or_else(|error| panic!("Failed to build PistonWindow: {}", error)));
+    ///
+    ///     let interval_id = prod.set_interval(1000, Box::new(|| eprintln!("One second.")));
+    ///
+    ///     prod.set_timeout(5000, Box::new(|| eprintln!("Five seconds.")));
+    ///     prod.cancel(interval_id);
+    /// # }
+    /// ```
+    pub fn set_interval(&mut self, interval: u64, callback: TimerCallback) -> TimerId {
+        self.scheduler.borrow_mut().set_interval(interval, callback)
+    }
+
+    /// Cancels a timeout or interval by its `TimerId`.  Returns `true` if the callback was
+    /// still scheduled, `false` otherwise.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        self.scheduler.borrow_mut().cancel(id)
+    }
+
+    /// Retrieves the `Clock` used by the scheduler and the animator.
+    pub fn get_clock(&self) -> Rc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Sets the `Theme` that provides default configuration values, such as colors, borders and
+    /// fonts, for all widgets.  Values set explicitly on a widget take precedence over the theme.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.widget_store.set_theme(theme);
+    }
+
+    /// Loads a layout file with `load_layout`, creating its widgets with the specified
+    /// `WidgetRegistry`, and reloads it during the update phase of the run loop whenever the file
+    /// changes on disk.  Returns the IDs of the named widgets in the layout.  If the file cannot be
+    /// reloaded, the previous widgets are kept, and the error is drawn over the window until the
+    /// file is fixed.  See `HotReloader` for details.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use piston_window::*;
+    /// # use pushrod::core::main::*;
+    /// # use pushrod::widget::registry::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let window: PistonWindow = WindowSettings::new("Pushrod Window", [640, 480])
+    ///             .opengl(OpenGL::V3_2)
+    ///             .build()
+    ///             .unwrap_or_else(|error| panic!("Failed to build PistonWindow: {}", error));
+    ///     let registry = WidgetRegistry::with_factory(window.factory.clone());
+    ///     let mut prod: Pushrod = Pushrod::new(window);
+    ///
+    ///     // Errors are also drawn over the window until the files are fixed.
+    ///     if let Err(error) = prod.watch_layout("assets/main.json", registry) {
+    ///         eprintln!("{}", error);
+    ///     }
+    ///
+    ///     if let Err(error) = prod.watch_stylesheet("assets/main.css") {
+    ///         eprintln!("{}", error);
+    ///     }
+    ///
+    ///     // The callback is attached again every time the layout is reloaded.
+    ///     prod.attach("ok", Box::new(|widget| {
+    ///         widget.on_mouse_entered(Box::new(|widget_id| eprintln!("Entered {}", widget_id)));
+    ///     }));
+    ///
+    ///     prod.run();
+    /// # }
+    /// ```
+    pub fn watch_layout<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        registry: WidgetRegistry,
+    ) -> Result<HashMap<String, i32>, LayoutError> {
+        self.hot_reloader
+            .watch_layout(&mut self.widget_store, path, registry)
+    }
+
+    /// Loads a style sheet file into the theme, and reloads it during the update phase of the
+    /// run loop whenever the file changes on disk.  The theme is built from all of the watched
+    /// style sheets, replacing the theme set with `set_theme`.
+    pub fn watch_stylesheet<P: AsRef<Path>>(&mut self, path: P) -> Result<(), StylesheetError> {
+        self.hot_reloader
+            .watch_stylesheet(&mut self.widget_store, path)
+    }
+
+    /// Registers a closure that is called with the widget of the specified name every time it is
+    /// loaded from a watched layout, used to attach callbacks by name.  The closure is called
+    /// immediately if the widget already exists.
+    pub fn attach(&mut self, name: &str, callback: AttachCallback) {
+        self.hot_reloader
+            .attach(&mut self.widget_store, name, callback);
+    }
+
+    /// Animates a configuration value of the widget specified by `widget_id`, from its current
+    /// value to `target`, over `duration` milliseconds, using the specified `Easing` curve.
+    /// Returns the `AnimationId` of the animation.
+    ///
+    /// Example:
+    /// ```no_run
+    /// # use piston_window::*;
+    /// # use pushrod::core::animation::*;
+    /// # use pushrod::core::main::*;
+    /// # use pushrod::core::point::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut prod: Pushrod = Pushrod::new(WindowSettings::new("Pushrod Window", [640, 480])
+    ///             .opengl(OpenGL::V3_2)
+    ///             .build()
+    ///             .unwrap_or_else(|error| panic!("Failed to build PistonWindow: {}", error)));
+    ///     let widget_id = prod.widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///
+    ///     // Fade the widget to red while moving it, then move it back.
+    ///     prod.animate(widget_id, CONFIG_COLOR, [1.0, 0.0, 0.0, 1.0], 500, Easing::Linear);
+    ///     let move_id = prod.animate(widget_id, CONFIG_ORIGIN, Point { x: 100, y: 100 }, 500,
+    ///         Easing::EaseInOutQuad);
+    ///     prod.animate_after(move_id, widget_id, CONFIG_ORIGIN, Point { x: 0, y: 0 }, 500,
+    ///         Easing::EaseInOutQuad);
+    ///
+    ///     prod.run();
+    /// # }
+    /// ```
+    pub fn animate<T: Tween>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        target: T,
+        duration: u64,
+        easing: Easing,
+    ) -> AnimationId {
+        self.animator
+            .animate(widget_id, key, target, duration, easing)
+    }
+
+    /// Animates a configuration value in the same way as `animate`, but only once the animation
+    /// specified by `previous` has completed.  Returns the `AnimationId` of the animation.
+    pub fn animate_after<T: Tween>(
+        &mut self,
+        previous: AnimationId,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        target: T,
+        duration: u64,
+        easing: Easing,
+    ) -> AnimationId {
+        self.animator
+            .animate_after(previous, widget_id, key, target, duration, easing)
+    }
+
+    /// Sets the closure action to be performed when an animation completes.  The closure
+    /// receives the ID of the animated widget.
+    pub fn on_animation_complete(&mut self, id: AnimationId, callback: SingleCallback) {
+        self.animator.on_complete(id, callback);
+    }
+
+    /// Cancels an animation by its `AnimationId`.  Returns `true` if the animation existed.
+    pub fn cancel_animation(&mut self, id: AnimationId) -> bool {
+        self.animator.cancel(id)
+    }
+
     fn handle_draw(&mut self, event: &Event) {
         let widgets = &mut self.widget_store;
+        let error = self.hot_reloader.get_error();
+
+        // The font for the error overlay is only loaded once an error occurs, and only once: if
+        // it cannot be loaded, the overlay is drawn without its message.
+        if error.is_some() && self.error_font.is_none() {
+            let font = TextWidget::load_font(
+                self.window.factory.clone(),
+                &CONFIG_FONT_NAME.default_value(),
+            );
+
+            if let Err(message) = &font {
+                eprintln!("Unable to draw error messages: {}", message);
+            }
 
-        self.window.draw_2d(event, |c, g| widgets.draw(0, c, g));
+            self.error_font = Some(font);
+        }
+
+        let width = self.window.draw_size()[0] as f64;
+        let error_font = self.error_font.as_mut().and_then(|x| x.as_mut().ok());
+
+        self.window.draw_2d(event, |c, g| {
+            widgets.draw(0, c, g);
+
+            if let Some(error) = error {
+                draw_error_overlay(error, width, error_font, c, g);
+            }
+        });
     }
 
     /// This is the main run loop that is called to process all UI events.  This loop is responsible
@@ -176,15 +412,25 @@ impl Pushrod {
     ///   - Button events
     ///   - Scroll button events
     /// - Custom events are then dispatched to any registered event listeners
+    /// - Update loop
+    ///   - Watched layout and style sheet files that have changed on disk are reloaded
+    ///   - Scheduled timeouts and intervals that have elapsed are called
+    ///   - Changed `Property` values are applied to their bound widgets
+    ///   - Running animations are advanced
+    ///   - Inherited configuration values are passed from parent widgets to their children
+    ///   - A hovered widget that has been hidden or disabled receives a mouse exit
+    ///   - Themes are re-applied to widgets whose state has changed
+    ///   - Widgets whose size has changed are notified through `on_resized`
+    ///   - Widgets are updated, in the order they were added
     /// - Draw loop
     ///   - Draw only widgets whose states have become invalidated
+    ///   - Draw the error of a watched file that could not be reloaded over the window
     ///   - Swap display buffers if required
     ///
     /// This event is handled window-by-window.  Once a window has processed all of its pending
     /// events, the next window is then processed.  No particular window takes precidence - any
     /// window that has events to process gets handled in order.
     pub fn run(&mut self) {
-        let mut last_widget_id = -1;
         let mut previous_mouse_position: Point = make_origin_point();
 //        let draw_size = self.window.draw_size();
 
@@ -211,15 +457,23 @@ impl Pushrod {
                             .mouse_moved_for_id(current_widget_id, mouse_point.clone());
                     }
 
-                    if current_widget_id != last_widget_id {
-                        if last_widget_id != -1 {
-                            self.widget_store.mouse_exited_for_id(last_widget_id);
-                        }
+                    // Disabled widgets are never hovered, so they receive neither a mouse
+                    // enter nor a mouse exit.
+                    let hover_widget_id = if current_widget_id != -1
+                        && self.widget_store.is_widget_enabled(current_widget_id)
+                    {
+                        current_widget_id
+                    } else {
+                        -1
+                    };
+                    let last_widget_id = self.widget_store.get_hovered_id();
 
-                        last_widget_id = current_widget_id;
+                    if hover_widget_id != last_widget_id {
+                        self.widget_store.exit_hovered();
+                        self.widget_store.set_hovered(hover_widget_id);
 
-                        if last_widget_id != -1 {
-                            self.widget_store.mouse_entered_for_id(last_widget_id);
+                        if hover_widget_id != -1 {
+                            self.widget_store.mouse_entered_for_id(hover_widget_id);
                         }
 
                         eprintln!(
@@ -236,14 +490,29 @@ impl Pushrod {
 //                self.internal_handle_mouse_button(button);
 //            });
 
+            event.button(|button| {
+                if let Button::Mouse(_) = button.button {
+                    match button.state {
+                        ButtonState::Press => {
+                            let hovered_id = self.widget_store.get_hovered_id();
+
+                            self.widget_store.set_pressed(hovered_id);
+                        }
+                        ButtonState::Release => self.widget_store.set_pressed(-1),
+                    }
+                }
+            });
+
             event.mouse_scroll(|x, y| {
                 let mouse_point = make_point_f64(x, y);
 
 //                self.internal_handle_mouse_scroll(mouse_point.clone());
 
-                if last_widget_id != -1 {
+                let hovered_id = self.widget_store.get_hovered_id();
+
+                if hovered_id != -1 {
                     self.widget_store
-                        .mouse_scrolled_for_id(last_widget_id, mouse_point.clone());
+                        .mouse_scrolled_for_id(hovered_id, mouse_point.clone());
                 }
             });
 
@@ -254,8 +523,25 @@ impl Pushrod {
             // Dispatch events here in the bus
 //            self.internal_dispatch_events();
 
+            // UPS loop handling
+
+            event.update(|args| {
+                self.hot_reloader.update(&mut self.widget_store);
+                self.scheduler.borrow_mut().tick();
+                self.widget_store.update_bindings();
+                self.animator.update(&mut self.widget_store);
+                self.widget_store.update_inherited_values();
+                self.widget_store.update_hovered();
+                self.widget_store.update_widget_states();
+                self.widget_store.update_widget_sizes();
+                self.widget_store.update_all_widgets(args.dt);
+            });
+
             // FPS loop handling
 
+            // Widgets are drawn directly to the window's back buffer, which does not keep the
+            // previous frame once the buffers are swapped, so every widget is drawn again on the
+            // next frame.
             event.render(|_| {
                 self.handle_draw(&event);
                 self.widget_store.invalidate_all_widgets();
@@ -28,3 +28,32 @@ pub mod widget_store;
 /// This is a per-widget callback store that is used to call closures when an event is
 /// triggered.
 pub mod callbacks;
+
+/// Contains the `Clock` trait, along with a `RealClock` and a `ManualClock` implementation.
+/// Time-based functionality reads time from a `Clock` so that it can be tested deterministically.
+pub mod clock;
+
+/// Contains the `Scheduler`, which calls closures after a timeout or at a repeating interval.
+/// The `Pushrod` run loop drives its scheduler during the update phase of each loop.
+pub mod scheduler;
+
+/// Contains the `Property`, an observable value that can be bound to `Widget` configuration
+/// values through the `WidgetStore`, for one-way and two-way data binding.
+pub mod property;
+
+/// Contains the `Animator`, which tweens `Widget` configuration values such as origin, size and
+/// color over time, using a set of `Easing` curves.
+pub mod animation;
+
+/// Selector-based queries, used to find widgets in a `WidgetStore` by their type, name,
+/// classes, states and position in the widget tree.
+pub mod query;
+
+/// Contains the `HotReloader`, which reloads layout and style sheet files into a `WidgetStore`
+/// when they change on disk during development.
+pub mod hot_reload;
+
+/// Contains the `CommandHistory`, which records reversible `Command` objects that change a
+/// `WidgetStore`, such as configuration changes and added, removed or moved widgets, so that
+/// they can be undone and redone.
+pub mod command;
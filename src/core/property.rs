@@ -0,0 +1,204 @@
+// Property
+// Observable values that can be bound to widget configuration
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::widget::config::*;
+use crate::widget::widget::*;
+
+/// Identifier assigned to a listener added to a `Property`.
+pub type ListenerId = u32;
+
+/// Identifier assigned to a binding between a `Property` and a widget's configuration.
+pub type BindingId = u32;
+
+/// Internal storage of a `Property`, shared between all of its handles.
+struct PropertyInner<T> {
+    value: T,
+    version: u64,
+    next_listener_id: ListenerId,
+    listeners: Vec<(ListenerId, Rc<dyn Fn(&T)>)>,
+}
+
+/// This is a `Property`, an observable value that is shared by all of its clones.  Application
+/// models can store their values in `Property` objects, and bind them to widget configuration
+/// values using `WidgetStore::bind`, so that widgets are updated whenever the model changes.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::property::*;
+/// # fn main() {
+///     let name = Property::new("Pushrod".to_string());
+///     let model_name = name.clone();
+///
+///     name.subscribe(Box::new(|value| eprintln!("Name changed to {}", value)));
+///     model_name.set("Pushrod UI".to_string());
+///
+///     assert_eq!(name.get(), "Pushrod UI");
+/// # }
+/// ```
+pub struct Property<T> {
+    inner: Rc<RefCell<PropertyInner<T>>>,
+}
+
+impl<T> Clone for Property<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Implementation of the `Property`.
+impl<T: Clone + PartialEq + 'static> Property<T> {
+    /// Creates a new `Property` containing the specified value.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(PropertyInner {
+                value,
+                version: 0,
+                next_listener_id: 1,
+                listeners: Vec::new(),
+            })),
+        }
+    }
+
+    /// Retrieves a copy of the current value.
+    pub fn get(&self) -> T {
+        self.inner.borrow().value.clone()
+    }
+
+    /// Sets the value.  If the value differs from the current value, all listeners are called
+    /// with the new value, and any bound widgets are updated during the next update phase.
+    pub fn set(&self, value: T) {
+        let listeners: Vec<Rc<dyn Fn(&T)>> = {
+            let mut inner = self.inner.borrow_mut();
+
+            if inner.value == value {
+                return;
+            }
+
+            inner.value = value.clone();
+            inner.version += 1;
+            inner.listeners.iter().map(|x| x.1.clone()).collect()
+        };
+
+        for listener in listeners.iter() {
+            listener(&value);
+        }
+    }
+
+    /// Retrieves the version of the value, which increases every time the value changes.
+    pub fn version(&self) -> u64 {
+        self.inner.borrow().version
+    }
+
+    /// Adds a listener that is called with the new value whenever the value changes.  Returns
+    /// the `ListenerId` of the listener.
+    pub fn subscribe(&self, listener: Box<dyn Fn(&T)>) -> ListenerId {
+        let mut inner = self.inner.borrow_mut();
+        let id = inner.next_listener_id;
+
+        inner.next_listener_id += 1;
+        inner.listeners.push((id, Rc::from(listener)));
+
+        id
+    }
+
+    /// Removes a listener by its `ListenerId`.
+    pub fn unsubscribe(&self, id: ListenerId) {
+        self.inner.borrow_mut().listeners.retain(|x| x.0 != id);
+    }
+}
+
+/// Internal trait used by the `WidgetStore` to store bindings of different value types together.
+pub(crate) trait Binding {
+    /// Applies the property's value to the widget if it has changed since it was last applied.
+    fn apply(&self, widget: &mut dyn Widget);
+
+    /// Retrieves the `ObserverId` of the widget observer used for two-way bindings.
+    fn observer_id(&self) -> Option<ObserverId>;
+}
+
+/// Internal binding of a `Property<T>` to a widget configuration value stored under a
+/// `ConfigKey<U>`, converting values using `to_widget`.
+pub(crate) struct PropertyBinding<T, U> {
+    property: Property<T>,
+    key: ConfigKey<U>,
+    to_widget: Box<dyn Fn(&T) -> U>,
+    applied_version: Rc<Cell<Option<u64>>>,
+    observer_id: Option<ObserverId>,
+}
+
+/// Implementation of the `PropertyBinding`.
+impl<T: Clone + PartialEq + 'static, U: 'static> PropertyBinding<T, U> {
+    /// Creates a one-way binding, which copies the property's value into the widget.
+    pub(crate) fn new(
+        property: &Property<T>,
+        key: ConfigKey<U>,
+        to_widget: Box<dyn Fn(&T) -> U>,
+    ) -> Self {
+        Self {
+            property: property.clone(),
+            key,
+            to_widget,
+            applied_version: Rc::new(Cell::new(None)),
+            observer_id: None,
+        }
+    }
+}
+
+/// Implementation of the two-way `PropertyBinding`.
+impl<T: Clone + PartialEq + 'static, U: PartialEq + 'static> PropertyBinding<T, U> {
+    /// Adds an observer to the widget's configuration, copying changes made to the widget's
+    /// value back into the property, converting values using `from_widget`.
+    pub(crate) fn observe_widget(
+        &mut self,
+        widget: &mut dyn Widget,
+        from_widget: Box<dyn Fn(&U) -> T>,
+    ) {
+        let property = self.property.clone();
+        let applied_version = self.applied_version.clone();
+
+        self.observer_id = Some(widget.config().observe(
+            self.key,
+            Box::new(move |_, new| {
+                property.set(from_widget(new));
+
+                // The widget already contains this value, so it does not need to be applied.
+                applied_version.set(Some(property.version()));
+            }),
+        ));
+    }
+}
+
+impl<T: Clone + PartialEq + 'static, U: 'static> Binding for PropertyBinding<T, U> {
+    fn apply(&self, widget: &mut dyn Widget) {
+        let version = self.property.version();
+
+        if self.applied_version.get() == Some(version) {
+            return;
+        }
+
+        // Mark the version as applied first, so a two-way observer does not set it again.
+        self.applied_version.set(Some(version));
+        set_widget_config(widget, self.key, (self.to_widget)(&self.property.get()));
+    }
+
+    fn observer_id(&self) -> Option<ObserverId> {
+        self.observer_id
+    }
+}
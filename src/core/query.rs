@@ -0,0 +1,359 @@
+// Widget Query
+// Selector-based queries over the widgets in a WidgetStore
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::core::widget_store::*;
+
+/// A state that a widget can be queried by, written as `:state` in a query.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PseudoClass {
+    Enabled,
+    Disabled,
+    Visible,
+    Hidden,
+    Hover,
+    Pressed,
+    Focused,
+}
+
+/// A selector for a single widget: `type#name.class:state`, where every part is optional, and
+/// any number of classes and states can be given.
+#[derive(Clone, PartialEq, Debug)]
+struct CompoundSelector {
+    widget_type: Option<String>,
+    name: Option<String>,
+    classes: Vec<String>,
+    pseudo_classes: Vec<PseudoClass>,
+}
+
+/// How a compound selector relates to the compound selector before it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Combinator {
+    /// `a b`: the widget is a descendant of a widget matching `a`.
+    Descendant,
+
+    /// `a > b`: the widget is a child of a widget matching `a`.
+    Child,
+}
+
+/// A chain of compound selectors.  The first combinator is ignored.
+type ComplexSelector = Vec<(Combinator, CompoundSelector)>;
+
+/// This is a `WidgetQuery`, a parsed selector that is used to find widgets in a `WidgetStore`
+/// by their type, name, classes, states and position in the widget tree.  Queries are usually
+/// run with `WidgetStore::query`, but can be parsed once with `WidgetQuery::parse` and run
+/// many times with `WidgetStore::query_with`.
+///
+/// The syntax is a subset of CSS selectors.  A query is a comma-separated list of selectors,
+/// matching widgets that match any of them.  A selector is a chain of widget selectors,
+/// separated by a space (the widget on the right is a descendant of the widget on the left)
+/// or by `>` (the widget on the right is a child of the widget on the left).
+///
+/// A widget selector consists of an optional widget type (or `*` for any type), followed by
+/// any of `#name`, `.class`, and the states `:enabled`, `:disabled`, `:visible`, `:hidden`,
+/// `:hover`, `:pressed` and `:focused`.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let mut toolbar = BaseWidget::new();
+///
+///     toolbar.set_name("toolbar");
+///
+///     let toolbar_id = widget_store.add_widget(Box::new(toolbar)).unwrap();
+///     let mut button_ids = Vec::new();
+///
+///     for _ in 0..3 {
+///         let mut button = BoxWidget::new();
+///
+///         button.add_class("button");
+///
+///         let button_id = widget_store.add_widget_to_parent(Box::new(button), toolbar_id);
+///
+///         button_ids.push(button_id.unwrap());
+///     }
+///
+///     widget_store.widgets[button_ids[1] as usize].widget.set_enabled(false);
+///
+///     assert_eq!(widget_store.query("#toolbar > .button:enabled").unwrap(),
+///         vec![button_ids[0], button_ids[2]]);
+///     assert_eq!(widget_store.query("box:disabled").unwrap(), vec![button_ids[1]]);
+///     assert_eq!(widget_store.query("base box").unwrap(), button_ids);
+///
+///     // Disable all of the buttons in the toolbar.
+///     for widget_id in widget_store.query("#toolbar .button").unwrap() {
+///         widget_store.widgets[widget_id as usize].widget.set_enabled(false);
+///     }
+///
+///     assert!(widget_store.query("#toolbar > .button:enabled").unwrap().is_empty());
+///     assert!(widget_store.query("#toolbar >").is_err());
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub struct WidgetQuery {
+    selectors: Vec<ComplexSelector>,
+}
+
+/// Implementation of the `WidgetQuery`.
+impl WidgetQuery {
+    /// Parses a query.  Returns a `WidgetStoreError::InvalidQuery` error containing the column
+    /// (starting at 1) of the error if the query is not valid.
+    pub fn parse(query: &str) -> Result<Self, WidgetStoreError> {
+        Parser {
+            chars: query.chars().collect(),
+            pos: 0,
+        }
+        .parse()
+    }
+
+    /// Indicates whether or not the `Widget` specified by its ID matches this query.
+    pub fn matches(&self, widget_store: &mut WidgetStore, widget_id: i32) -> bool {
+        self.selectors
+            .iter()
+            .any(|x| Self::matches_complex(widget_store, widget_id, x))
+    }
+
+    fn matches_complex(
+        widget_store: &mut WidgetStore,
+        widget_id: i32,
+        selector: &[(Combinator, CompoundSelector)],
+    ) -> bool {
+        let (combinator, compound) = selector.last().unwrap();
+
+        if !Self::matches_compound(widget_store, widget_id, compound) {
+            return false;
+        }
+
+        let rest = &selector[..selector.len() - 1];
+
+        if rest.is_empty() {
+            return true;
+        }
+
+        let mut current_id = widget_id;
+
+        loop {
+            let parent_id = widget_store.get_parent_of(current_id);
+
+            if parent_id == current_id {
+                return false;
+            }
+
+            if Self::matches_complex(widget_store, parent_id, rest) {
+                return true;
+            }
+
+            if *combinator == Combinator::Child {
+                return false;
+            }
+
+            current_id = parent_id;
+        }
+    }
+
+    fn matches_compound(
+        widget_store: &mut WidgetStore,
+        widget_id: i32,
+        selector: &CompoundSelector,
+    ) -> bool {
+        let matches_widget = {
+            let widget = &mut widget_store.widgets[widget_id as usize].widget;
+
+            selector
+                .widget_type
+                .as_ref()
+                .map_or(true, |x| x == widget.get_widget_type())
+                && selector
+                    .name
+                    .as_ref()
+                    .map_or(true, |x| widget.get_name().as_ref() == Some(x))
+                && selector.classes.iter().all(|x| widget.has_class(x))
+        };
+
+        matches_widget
+            && selector.pseudo_classes.iter().all(|x| match x {
+                PseudoClass::Enabled => widget_store.is_widget_enabled(widget_id),
+                PseudoClass::Disabled => !widget_store.is_widget_enabled(widget_id),
+                PseudoClass::Visible => widget_store.is_widget_visible(widget_id),
+                PseudoClass::Hidden => !widget_store.is_widget_visible(widget_id),
+                PseudoClass::Hover => widget_store.get_hovered_id() == widget_id,
+                PseudoClass::Pressed => widget_store.get_pressed_id() == widget_id,
+                PseudoClass::Focused => widget_store.get_focused_id() == widget_id,
+            })
+    }
+}
+
+/// Internal parser for queries, which keeps track of the column for error reporting.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, WidgetStoreError> {
+        Err(WidgetStoreError::InvalidQuery {
+            column: self.pos + 1,
+            message,
+        })
+    }
+
+    fn describe(c: Option<char>) -> String {
+        match c {
+            Some(c) => format!("'{}'", c),
+            None => "end of query".to_string(),
+        }
+    }
+
+    /// Skips whitespace, returning `true` if any was skipped.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.pos += 1;
+        }
+
+        self.pos != start
+    }
+
+    fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '-' || c == '_'
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String, WidgetStoreError> {
+        let start = self.pos;
+
+        while self.peek().map_or(false, Self::is_ident_char) {
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            self.error(format!(
+                "Expected {}, found {}",
+                what,
+                Self::describe(self.peek())
+            ))
+        } else {
+            Ok(self.chars[start..self.pos].iter().collect())
+        }
+    }
+
+    fn parse(&mut self) -> Result<WidgetQuery, WidgetStoreError> {
+        let mut selectors = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.complex()?);
+
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                None => return Ok(WidgetQuery { selectors }),
+                c => return self.error(format!("Unexpected {}", Self::describe(c))),
+            }
+        }
+    }
+
+    /// Parses a chain of compound selectors, up to a comma or the end of the query.
+    fn complex(&mut self) -> Result<ComplexSelector, WidgetStoreError> {
+        let mut selector = vec![(Combinator::Descendant, self.compound()?)];
+
+        loop {
+            let skipped = self.skip_whitespace();
+
+            let combinator = match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    Combinator::Child
+                }
+                Some(',') | None => return Ok(selector),
+                _ if skipped => Combinator::Descendant,
+                c => return self.error(format!("Unexpected {}", Self::describe(c))),
+            };
+
+            selector.push((combinator, self.compound()?));
+        }
+    }
+
+    /// Parses a single compound selector, such as `box#ok.button:enabled`.
+    fn compound(&mut self) -> Result<CompoundSelector, WidgetStoreError> {
+        let start = self.pos;
+        let mut selector = CompoundSelector {
+            widget_type: None,
+            name: None,
+            classes: Vec::new(),
+            pseudo_classes: Vec::new(),
+        };
+
+        if self.peek() == Some('*') {
+            self.pos += 1;
+        } else if self.peek().map_or(false, Self::is_ident_char) {
+            selector.widget_type = Some(self.ident("a widget type")?);
+        }
+
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    if selector.name.is_some() {
+                        return self.error("Only one name is allowed in a selector".to_string());
+                    }
+
+                    self.pos += 1;
+                    selector.name = Some(self.ident("a widget name")?);
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    selector.classes.push(self.ident("a class name")?);
+                }
+                Some(':') => {
+                    self.pos += 1;
+
+                    let column = self.pos;
+                    let pseudo_class = match self.ident("a state")?.as_str() {
+                        "enabled" => PseudoClass::Enabled,
+                        "disabled" => PseudoClass::Disabled,
+                        "visible" => PseudoClass::Visible,
+                        "hidden" => PseudoClass::Hidden,
+                        "hover" => PseudoClass::Hover,
+                        "pressed" => PseudoClass::Pressed,
+                        "focused" => PseudoClass::Focused,
+                        other => {
+                            self.pos = column;
+                            return self.error(format!("Unknown state '{}'", other));
+                        }
+                    };
+
+                    selector.pseudo_classes.push(pseudo_class);
+                }
+                _ => break,
+            }
+        }
+
+        if self.pos == start {
+            self.error(format!(
+                "Expected a selector, found {}",
+                Self::describe(self.peek())
+            ))
+        } else {
+            Ok(selector)
+        }
+    }
+}
@@ -0,0 +1,265 @@
+// Scheduler
+// Run loop scheduler for timeouts and intervals
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::core::clock::*;
+
+/// Identifier assigned to a scheduled timeout or interval.  Use this ID to `cancel` the
+/// callback before it is triggered.
+pub type TimerId = u32;
+
+/// Closure called by the `Scheduler` when a timeout or interval has elapsed.
+pub type TimerCallback = Box<dyn FnMut()>;
+
+/// A `Scheduler` shared between `Pushrod`, its `WidgetStore`, and the widgets that schedule
+/// callbacks, such as the `TimerWidget`.
+pub type SharedScheduler = Rc<RefCell<Scheduler>>;
+
+/// Indicates how many times a scheduled callback is called before it is removed from the
+/// `Scheduler`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimerRepeat {
+    /// Called a single time, then removed.
+    Once,
+
+    /// Called the specified number of times, then removed.
+    Times(u32),
+
+    /// Called repeatedly until cancelled.
+    Forever,
+}
+
+/// Internal structure used to track a single scheduled callback.
+struct ScheduledTimer {
+    id: TimerId,
+    interval: u64,
+    deadline: u64,
+    remaining_fires: Option<u32>,
+    paused_remaining: Option<u64>,
+    callback: TimerCallback,
+}
+
+/// This is the `Scheduler`, which stores a list of timeouts and intervals, and triggers their
+/// callbacks once their time has elapsed.  Time is read from a `Clock`, which defaults to a
+/// monotonic `RealClock`, so changes to the system's wall clock do not affect scheduled callbacks.
+///
+/// The `Pushrod` run loop shares its `Scheduler` with its `WidgetStore`, and calls `tick` during
+/// the update phase of each loop, so callbacks are not tied to the screen refresh rate.  The
+/// scheduler is borrowed while callbacks are called, so callbacks must not schedule or cancel
+/// timers on the same `SharedScheduler`.
+pub struct Scheduler {
+    clock: Rc<dyn Clock>,
+    next_id: TimerId,
+    timers: Vec<ScheduledTimer>,
+}
+
+/// Implementation of the `Scheduler`.
+///
+/// Example usage, using a `ManualClock` to control when callbacks fire:
+/// ```
+/// # use pushrod::core::clock::*;
+/// # use pushrod::core::scheduler::*;
+/// # use std::cell::Cell;
+/// # use std::rc::Rc;
+/// # fn main() {
+///     let clock = Rc::new(ManualClock::new());
+///     let mut scheduler = Scheduler::with_clock(clock.clone());
+///     let fired = Rc::new(Cell::new(0));
+///     let fired_interval = fired.clone();
+///
+///     let timeout_id = scheduler.set_timeout(500, Box::new(|| eprintln!("Timeout.")));
+///     let interval_id = scheduler.set_interval(100, Box::new(move || {
+///         fired_interval.set(fired_interval.get() + 1);
+///     }));
+///
+///     // Called by the run loop on every update.
+///     clock.advance(99);
+///     scheduler.tick();
+///     assert_eq!(fired.get(), 0);
+///
+///     clock.advance(1);
+///     scheduler.tick();
+///     assert_eq!(fired.get(), 1);
+///
+///     clock.advance(400);
+///     scheduler.tick();
+///     assert!(!scheduler.is_scheduled(timeout_id));
+///
+///     assert!(scheduler.cancel(interval_id));
+///     assert!(!scheduler.is_scheduled(interval_id));
+/// # }
+/// ```
+impl Scheduler {
+    /// Creates a new `Scheduler` with no scheduled callbacks, using a `RealClock`.
+    pub fn new() -> Self {
+        Self::with_clock(Rc::new(RealClock::new()))
+    }
+
+    /// Creates a new `Scheduler` with no scheduled callbacks, reading time from the specified
+    /// `Clock`.
+    pub fn with_clock(clock: Rc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            next_id: 1,
+            timers: Vec::new(),
+        }
+    }
+
+    /// Returns the current time of this `Scheduler`'s `Clock` in milliseconds.
+    fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// Schedules a callback to be called every `interval` milliseconds, as many times as
+    /// specified by `repeat`.  Returns the `TimerId` of the scheduled callback.
+    pub fn schedule(
+        &mut self,
+        interval: u64,
+        repeat: TimerRepeat,
+        callback: TimerCallback,
+    ) -> TimerId {
+        let id = self.next_id;
+        let remaining_fires = match repeat {
+            TimerRepeat::Once => Some(1),
+            TimerRepeat::Times(times) => Some(times),
+            TimerRepeat::Forever => None,
+        };
+
+        self.next_id += 1;
+        self.timers.push(ScheduledTimer {
+            id,
+            interval,
+            deadline: self.now() + interval,
+            remaining_fires,
+            paused_remaining: None,
+            callback,
+        });
+
+        id
+    }
+
+    /// Schedules a callback to be called once, after `timeout` milliseconds have elapsed.
+    /// Returns the `TimerId` of the scheduled callback.
+    pub fn set_timeout(&mut self, timeout: u64, callback: TimerCallback) -> TimerId {
+        self.schedule(timeout, TimerRepeat::Once, callback)
+    }
+
+    /// Schedules a callback to be called repeatedly, every `interval` milliseconds, until it
+    /// is cancelled.  Returns the `TimerId` of the scheduled callback.
+    pub fn set_interval(&mut self, interval: u64, callback: TimerCallback) -> TimerId {
+        self.schedule(interval, TimerRepeat::Forever, callback)
+    }
+
+    /// Cancels a scheduled timeout or interval.  Returns `true` if the callback was scheduled,
+    /// `false` if it had already fired or did not exist.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let scheduled = self.is_scheduled(id);
+
+        self.timers.retain(|x| x.id != id);
+
+        scheduled
+    }
+
+    /// Indicates whether or not a callback with the specified `TimerId` is still scheduled.
+    pub fn is_scheduled(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|x| x.id == id)
+    }
+
+    /// Pauses a scheduled callback, keeping the time remaining until its next deadline.
+    /// Returns `true` if the callback was scheduled and running, `false` otherwise.
+    pub fn pause(&mut self, id: TimerId) -> bool {
+        let now = self.now();
+
+        match self
+            .timers
+            .iter_mut()
+            .find(|x| x.id == id && x.paused_remaining.is_none())
+        {
+            Some(timer) => {
+                timer.paused_remaining = Some(timer.deadline.saturating_sub(now));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Resumes a paused callback, so that its next deadline is the time that remained when it
+    /// was paused.  Returns `true` if the callback was scheduled and paused, `false` otherwise.
+    pub fn resume(&mut self, id: TimerId) -> bool {
+        let now = self.now();
+
+        match self.timers.iter_mut().find(|x| x.id == id) {
+            Some(timer) => match timer.paused_remaining.take() {
+                Some(remaining) => {
+                    timer.deadline = now + remaining;
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Indicates whether or not a scheduled callback is paused.
+    pub fn is_paused(&self, id: TimerId) -> bool {
+        self.timers
+            .iter()
+            .any(|x| x.id == id && x.paused_remaining.is_some())
+    }
+
+    /// Retrieves the number of milliseconds remaining until a scheduled callback is next called.
+    /// Returns `None` if the callback is not scheduled.
+    pub fn remaining(&self, id: TimerId) -> Option<u64> {
+        let now = self.now();
+
+        self.timers
+            .iter()
+            .find(|x| x.id == id)
+            .map(|x| match x.paused_remaining {
+                Some(remaining) => remaining,
+                None => x.deadline.saturating_sub(now),
+            })
+    }
+
+    /// Triggers the callbacks of any timeouts or intervals that have elapsed.  Repeating
+    /// callbacks are rescheduled relative to their previous deadline, so if a tick arrives late,
+    /// the callback is called once for every interval that has elapsed since.  Callbacks that
+    /// have been called the number of times specified by their `TimerRepeat` are removed.
+    pub fn tick(&mut self) {
+        let now = self.now();
+
+        for timer in self.timers.iter_mut() {
+            if timer.paused_remaining.is_some() {
+                continue;
+            }
+
+            while now >= timer.deadline && timer.remaining_fires != Some(0) {
+                (timer.callback)();
+
+                timer.remaining_fires = timer.remaining_fires.map(|x| x - 1);
+
+                if timer.interval == 0 {
+                    timer.deadline = now + 1;
+                } else {
+                    timer.deadline += timer.interval;
+                }
+            }
+        }
+
+        self.timers.retain(|x| x.remaining_fires != Some(0));
+    }
+}
@@ -13,10 +13,65 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::core::callbacks::*;
 use crate::core::point::*;
+use crate::core::property::*;
+use crate::core::query::*;
+use crate::core::scheduler::*;
+use crate::widget::builder::*;
+use crate::widget::config::*;
+use crate::widget::layout::*;
+use crate::widget::theme::*;
 use crate::widget::widget::*;
 
 use piston_window::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+/// An error returned by a `WidgetStore` operation.
+#[derive(Clone, PartialEq, Debug)]
+pub enum WidgetStoreError {
+    /// Another widget in the store already has the specified name.
+    DuplicateName(String),
+
+    /// The widget specified by its ID cannot be used as a parent: it does not exist, it has been
+    /// removed, or it is the widget being moved or one of its descendants.
+    InvalidParent(i32),
+
+    /// The widget specified by its ID does not exist, or has been removed.
+    InvalidWidget(i32),
+
+    /// A `WidgetQuery` could not be parsed.  The column (starting at 1) points to the location
+    /// of the error.
+    InvalidQuery { column: usize, message: String },
+}
+
+impl fmt::Display for WidgetStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WidgetStoreError::DuplicateName(name) => {
+                write!(f, "A widget named '{}' already exists", name)
+            }
+            WidgetStoreError::InvalidParent(parent_id) => {
+                write!(f, "Widget {} cannot be used as a parent", parent_id)
+            }
+            WidgetStoreError::InvalidWidget(widget_id) => {
+                write!(f, "There is no widget with ID {}", widget_id)
+            }
+            WidgetStoreError::InvalidQuery { column, message } => {
+                write!(f, "Invalid query at column {}: {}", column, message)
+            }
+        }
+    }
+}
+
+impl Error for WidgetStoreError {}
 
 /// This is a container object, used for storing the `Widget` trait object, and the parent
 /// relationship for the added `Widget`.  Only the `widget` is public.  `Widget` objects do not
@@ -31,34 +86,199 @@ pub struct WidgetContainer {
 
     /// The parent ID.
     parent_id: i32,
+
+    /// The position of the widget among its siblings, which are listed and drawn in this order,
+    /// then by ID.  This is the widget's ID unless another widget's position was given to it.
+    order: i32,
+
+    /// The `StyleTarget` that the widget's theme values were last resolved for.
+    style_target: Option<StyleTarget>,
+
+    /// The size of the widget when its size was last checked, used to call `on_resized`.
+    size: Size,
+
+    /// Whether the widget was added by the `build` function of a composite widget.  These
+    /// widgets are not saved to layout files, as they are rebuilt when the layout is loaded.
+    built: bool,
+
+    /// Whether all of the widget's ancestors were enabled when this was last checked, used to
+    /// call `on_parent_enabled_changed`.
+    parent_enabled: bool,
+
+    /// Whether the widget has been removed.  The `widget` of a removed container is a hidden,
+    /// disabled placeholder.
+    removed: bool,
+}
+
+/// Placeholder stored in place of a widget that has been removed, so that the IDs of the other
+/// widgets do not change.  It is hidden, disabled, and not part of the widget tree.
+struct RemovedWidget {
+    config: Configurable,
+    callbacks: CallbackStore,
+}
+
+impl RemovedWidget {
+    fn new() -> Self {
+        let mut config = Configurable::new();
+
+        config.set(CONFIG_VISIBLE, false);
+        config.set(CONFIG_ENABLED, false);
+
+        Self {
+            config,
+            callbacks: CallbackStore::new(),
+        }
+    }
+}
+
+impl Widget for RemovedWidget {
+    fn config(&mut self) -> &mut Configurable {
+        &mut self.config
+    }
+
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
+    fn callbacks(&mut self) -> &mut CallbackStore {
+        &mut self.callbacks
+    }
+
+    fn get_widget_type(&self) -> &'static str {
+        "removed"
+    }
+
+    fn draw(&mut self, _context: Context, _graphics: &mut G2d) {}
+}
+
+/// Placeholder stored in place of a composite widget while its `build` function runs.  It
+/// carries the composite's type, name, classes, visibility, enabled state, bounds and
+/// inheritable values, so that children added during the build inherit from the composite,
+/// and names and queries checked during the build see it.
+struct BuildingWidget {
+    config: Configurable,
+    callbacks: CallbackStore,
+    widget_type: &'static str,
+}
+
+impl BuildingWidget {
+    fn new(widget: &mut dyn Widget) -> Self {
+        let mut config = Configurable::new();
+        let origin = widget.get_origin();
+        let size = widget.get_size();
+
+        config.set(CONFIG_NAME, widget.config().get(CONFIG_NAME));
+        config.set(CONFIG_CLASSES, widget.get_classes());
+        config.set(CONFIG_VISIBLE, widget.is_visible());
+        config.set(CONFIG_ENABLED, widget.is_enabled());
+        config.set(CONFIG_ORIGIN, origin);
+        config.set(CONFIG_SIZE, size);
+        config.set_inherited_values(widget.config().get_inheritable_values());
+
+        Self {
+            config,
+            callbacks: CallbackStore::new(),
+            widget_type: widget.get_widget_type(),
+        }
+    }
+}
+
+impl Widget for BuildingWidget {
+    fn config(&mut self) -> &mut Configurable {
+        &mut self.config
+    }
+
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
+    fn callbacks(&mut self) -> &mut CallbackStore {
+        &mut self.callbacks
+    }
+
+    fn get_widget_type(&self) -> &'static str {
+        self.widget_type
+    }
+
+    fn draw(&mut self, _context: Context, _graphics: &mut G2d) {}
+}
+
+/// Widgets that were removed from a `WidgetStore` with `take_widget`, which can be put back in
+/// place with `restore_widget`.
+pub struct DetachedWidget {
+    /// The ID, parent ID, built flag and widget of each removed widget, in tree order.
+    widgets: Vec<(i32, i32, bool, Box<dyn Widget>)>,
 }
 
 /// This is the `WidgetStore`, which is used to store `Widget` objects for a `Pushrod`
 /// management object.
 pub struct WidgetStore {
-    /// A vector list of `WidgetContainer` objects.
+    /// A vector list of `WidgetContainer` objects.  Widget names must stay unique, so the names
+    /// of the widgets in this list can only be changed with `set_widget_name`.
     pub widgets: Vec<WidgetContainer>,
+
+    /// Bindings of `Property` objects to widget configuration values, by widget ID.
+    bindings: Vec<(BindingId, i32, Box<dyn Binding>)>,
+    next_binding_id: BindingId,
+
+    /// The `Theme` providing default configuration values for the widgets.
+    theme: Theme,
+
+    /// IDs of the widgets that are hovered, pressed and focused, or -1 if there are none.
+    hovered_id: i32,
+    pressed_id: i32,
+    focused_id: i32,
+
+    /// The `Scheduler` passed to widgets when they are added, shared with `Pushrod`.
+    scheduler: SharedScheduler,
 }
 
 /// Implementation of the `WidgetStore`.
 impl WidgetStore {
-    /// Creates a new `WidgetStore`.
+    /// Creates a new `WidgetStore`, with its own `Scheduler` using a `RealClock`.
     pub fn new() -> Self {
+        Self::with_scheduler(Rc::new(RefCell::new(Scheduler::new())))
+    }
+
+    /// Creates a new `WidgetStore` that passes the specified `Scheduler` to its widgets.  The
+    /// store does not tick the scheduler: this is done by its owner, such as the `Pushrod` run
+    /// loop.
+    pub fn with_scheduler(scheduler: SharedScheduler) -> Self {
         let mut widgets_list: Vec<WidgetContainer> = Vec::new();
         let mut base_widget = BaseWidget::new();
 
         base_widget.set_size(800, 600);
+        let size = base_widget.get_size();
+
         widgets_list.push(WidgetContainer {
             widget: Box::new(base_widget),
             widget_id: 0,
             parent_id: 0,
+            order: 0,
+            style_target: None,
+            size,
+            built: false,
+            parent_enabled: true,
+            removed: false,
         });
 
         Self {
             widgets: widgets_list,
+            bindings: Vec::new(),
+            next_binding_id: 1,
+            theme: Theme::new(),
+            hovered_id: -1,
+            pressed_id: -1,
+            focused_id: -1,
+            scheduler,
         }
     }
 
+    /// Retrieves the `Scheduler` that is passed to widgets when they are added to this store.
+    pub fn get_scheduler(&self) -> SharedScheduler {
+        self.scheduler.clone()
+    }
+
     /// Handles the resizing of the texture buffer after the window resize has taken place.  The
     /// behavior should be processed before drawing is rendered, so the sequence of events should
     /// be `event` -> `handle_resize` -> `invalidate` -> `draw`.  This is mainly handled by the
@@ -74,6 +294,34 @@ impl WidgetStore {
         self.widgets.iter_mut().for_each(|x| x.widget.invalidate());
     }
 
+    /// Updates all widgets in the window, calling each `Widget`'s `update` function with its ID
+    /// and the number of seconds elapsed since the previous update.  Widgets whose ancestors
+    /// have been disabled or enabled since the last update have `on_parent_enabled_changed`
+    /// called first.  This is called by the `Pushrod` run loop during the update phase, before
+    /// drawing.
+    pub fn update_all_widgets(&mut self, dt: f64) {
+        for widget_id in 0..self.widgets.len() as i32 {
+            // Removed widgets keep the last state reported to them, so that they are told about
+            // any change when they are restored.
+            if !self.is_existing_widget(widget_id) {
+                continue;
+            }
+
+            let parent_id = self.widgets[widget_id as usize].parent_id;
+            let parent_enabled = parent_id == widget_id || self.is_widget_enabled(parent_id);
+            let container = &mut self.widgets[widget_id as usize];
+
+            if container.parent_enabled != parent_enabled {
+                container.parent_enabled = parent_enabled;
+                container
+                    .widget
+                    .on_parent_enabled_changed(widget_id, parent_enabled);
+            }
+
+            container.widget.update(widget_id, dt);
+        }
+    }
+
     /// Indicates whether or not any `Widget`s in the `WidgetStore` have been invalidated and need
     /// to be repainted.
     pub fn needs_repaint(&mut self) -> bool {
@@ -88,34 +336,655 @@ impl WidgetStore {
     /// be part of the base widget (`id = 0`), and will be force-redrawn when the parent is
     /// invalidated.
     ///
-    /// After adding a widget, the ID of the widget is returned.
-    pub fn add_widget(&mut self, widget: Box<dyn Widget>) -> i32 {
-        let widget_size = self.widgets.len() as i32;
-
-        self.widgets.push(WidgetContainer {
-            widget,
-            widget_id: widget_size,
-            parent_id: 0,
-        });
-
-        widget_size
+    /// After adding a widget, the ID of the widget is returned.  Returns a
+    /// `WidgetStoreError::DuplicateName` error if the widget has a name that is already used by
+    /// another widget, in which case the widget is not added.
+    pub fn add_widget(&mut self, widget: Box<dyn Widget>) -> Result<i32, WidgetStoreError> {
+        self.add_widget_to_parent(widget, 0)
     }
 
     /// Adds a UI `Widget` to the parent of a window, specified by the `parent_id`.  The `parent_id`
     /// must be an object that already exists in the stack.
     ///
-    /// After adding a widget, the ID of the widget is returned.
-    pub fn add_widget_to_parent(&mut self, widget: Box<dyn Widget>, parent_id: i32) -> i32 {
-        // TODO Validate parent_id
+    /// After adding a widget, the ID of the widget is returned.  Returns a
+    /// `WidgetStoreError::DuplicateName` error if the widget has a name that is already used by
+    /// another widget, or a `WidgetStoreError::InvalidParent` error if the parent does not exist,
+    /// in which case the widget is not added.
+    ///
+    /// The widget's `on_scheduler_attached` and `on_added` functions are called, followed by its
+    /// `build` function.
+    pub fn add_widget_to_parent(
+        &mut self,
+        mut widget: Box<dyn Widget>,
+        parent_id: i32,
+    ) -> Result<i32, WidgetStoreError> {
+        if !self.is_existing_widget(parent_id) {
+            return Err(WidgetStoreError::InvalidParent(parent_id));
+        }
+
+        if let Some(name) = widget.get_name() {
+            if self.find_by_name(&name).is_some() {
+                return Err(WidgetStoreError::DuplicateName(name));
+            }
+        }
+
         let widget_size = self.widgets.len() as i32;
+        let size = widget.get_size();
+
+        widget.config().set_locked(CONFIG_NAME.id(), true);
 
         self.widgets.push(WidgetContainer {
             widget,
             widget_id: widget_size,
             parent_id,
+            order: widget_size,
+            style_target: None,
+            size,
+            built: false,
+            parent_enabled: true,
+            removed: false,
         });
+        self.apply_theme(widget_size);
+        self.inherit_values(widget_size);
+        self.widgets[widget_size as usize]
+            .widget
+            .on_scheduler_attached(&self.scheduler);
+        self.widgets[widget_size as usize]
+            .widget
+            .on_added(widget_size);
+        self.build_widget(widget_size);
+
+        Ok(widget_size)
+    }
+
+    /// Adds a tree of widgets, created with a `WidgetBuilder` or the `widgets!` macro, as
+    /// children of `parent_id`.  Each widget is added before its children, so widget IDs follow
+    /// the order of the tree.  Returns the IDs of the named widgets in the tree, by name.
+    ///
+    /// The parent and the names declared in the tree are checked before any widget is added.  If
+    /// adding fails part of the way through, for example because a composite widget's `build`
+    /// added a child with a name used further down the tree, the widgets added so far are removed
+    /// again before the error is returned.  Their IDs are not reused.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::box_widget::*;
+    /// # use pushrod::widget::widget::*;
+    /// # use pushrod::widgets;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let ids = widget_store.add_widgets(widgets![
+    ///         BaseWidget::builder().name("panel") => [
+    ///             BoxWidget::builder().name("ok"),
+    ///         ],
+    ///     ], 0).unwrap();
+    ///
+    ///     assert_eq!(ids["panel"], 1);
+    ///     assert_eq!(ids["ok"], 2);
+    ///
+    ///     let result = widget_store.add_widgets(widgets![
+    ///         BaseWidget::builder() => [
+    ///             BoxWidget::builder().name("ok"),
+    ///         ],
+    ///     ], 0);
+    ///
+    ///     assert_eq!(result, Err(WidgetStoreError::DuplicateName("ok".to_string())));
+    ///     assert_eq!(widget_store.widgets.len(), 3);
+    /// # }
+    /// ```
+    pub fn add_widgets(
+        &mut self,
+        mut nodes: Vec<WidgetNode>,
+        parent_id: i32,
+    ) -> Result<HashMap<String, i32>, WidgetStoreError> {
+        if !self.is_existing_widget(parent_id) {
+            return Err(WidgetStoreError::InvalidParent(parent_id));
+        }
+
+        let mut names = Vec::new();
+
+        for node in nodes.iter_mut() {
+            node.collect_names(&mut names)?;
+        }
+
+        if let Some(name) = names.into_iter().find(|x| self.find_by_name(x).is_some()) {
+            return Err(WidgetStoreError::DuplicateName(name));
+        }
+
+        let first_id = self.widgets.len() as i32;
+        let mut ids = HashMap::new();
+
+        for node in nodes {
+            if let Err(error) = node.add_to(self, parent_id, &mut ids) {
+                // Removing the widgets attached outside of the new ones also removes the rest.
+                let added_roots: Vec<i32> = (first_id..self.widgets.len() as i32)
+                    .filter(|x| {
+                        self.is_existing_widget(*x)
+                            && self.widgets[*x as usize].parent_id < first_id
+                    })
+                    .collect();
+
+                for widget_id in added_roots {
+                    self.remove_widget(widget_id);
+                }
+
+                return Err(error);
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Indicates whether or not the specified ID belongs to a widget in the store that has not
+    /// been removed.
+    pub(crate) fn is_existing_widget(&self, widget_id: i32) -> bool {
+        widget_id >= 0
+            && (widget_id as usize) < self.widgets.len()
+            && !self.widgets[widget_id as usize].removed
+    }
+
+    /// Moves the `Widget` specified by its ID, along with its descendants, to a new parent.  The
+    /// widget's `on_parent_changed` function is called, and the inherited values of the widget
+    /// and its descendants are updated.  Returns a `WidgetStoreError::InvalidParent` error if the
+    /// new parent does not exist, or is the widget itself or one of its descendants.  The base
+    /// widget (`id = 0`) cannot be moved.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let panel_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let other_panel_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let child_id = widget_store
+    ///         .add_widget_to_parent(Box::new(BaseWidget::new()), panel_id)
+    ///         .unwrap();
+    ///
+    ///     widget_store.widgets[other_panel_id as usize].widget.config()
+    ///         .set(CONFIG_FONT_SIZE, 24);
+    ///
+    ///     assert_eq!(widget_store.set_parent(panel_id, child_id),
+    ///         Err(WidgetStoreError::InvalidParent(child_id)));
+    ///     assert!(widget_store.set_parent(child_id, other_panel_id).is_ok());
+    ///     assert_eq!(widget_store.get_children_of(other_panel_id), vec![child_id]);
+    ///     let child = widget_store.get_widget_mut(child_id).unwrap();
+    ///
+    ///     assert_eq!(child.config().get(CONFIG_FONT_SIZE), 24);
+    /// # }
+    /// ```
+    pub fn set_parent(&mut self, widget_id: i32, parent_id: i32) -> Result<(), WidgetStoreError> {
+        if widget_id <= 0
+            || !self.is_existing_widget(widget_id)
+            || !self.is_existing_widget(parent_id)
+            || parent_id == widget_id
+            || self.get_descendants_of(widget_id).contains(&parent_id)
+        {
+            return Err(WidgetStoreError::InvalidParent(parent_id));
+        }
+
+        let old_parent_id = self.widgets[widget_id as usize].parent_id;
+
+        if old_parent_id == parent_id {
+            return Ok(());
+        }
+
+        self.widgets[widget_id as usize].parent_id = parent_id;
+        self.widgets[widget_id as usize]
+            .widget
+            .on_parent_changed(widget_id, old_parent_id, parent_id);
+        self.widgets[old_parent_id as usize].widget.invalidate();
+        self.widgets[widget_id as usize].widget.invalidate();
+
+        let mut widget_ids = vec![widget_id];
+
+        widget_ids.extend(self.get_descendants_of(widget_id));
+
+        for id in widget_ids {
+            self.inherit_values(id);
+        }
+
+        Ok(())
+    }
+
+    /// Calls `on_resized` for all widgets whose size has changed since the last time this was
+    /// called.  This is called by the `Pushrod` run loop during the update phase.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::callbacks::*;
+    /// # use pushrod::core::point::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # use std::cell::RefCell;
+    /// # use std::rc::Rc;
+    /// struct PanelWidget {
+    ///     config: Configurable,
+    ///     callbacks: CallbackStore,
+    ///     resizes: Rc<RefCell<Vec<(i32, i32)>>>,
+    /// }
+    ///
+    /// impl Widget for PanelWidget {
+    ///     fn config(&mut self) -> &mut Configurable {
+    ///         &mut self.config
+    ///     }
+    ///
+    ///     fn get_config(&self) -> &Configurable {
+    ///         &self.config
+    ///     }
+    ///
+    ///     fn callbacks(&mut self) -> &mut CallbackStore {
+    ///         &mut self.callbacks
+    ///     }
+    ///
+    ///     fn on_resized(&mut self, _widget_id: i32, _old_size: &Size, new_size: &Size) {
+    ///         self.resizes.borrow_mut().push((new_size.w, new_size.h));
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    ///     let resizes = Rc::new(RefCell::new(Vec::new()));
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let widget_id = widget_store.add_widget(Box::new(PanelWidget {
+    ///         config: Configurable::new(),
+    ///         callbacks: CallbackStore::new(),
+    ///         resizes: resizes.clone(),
+    ///     })).unwrap();
+    ///
+    ///     widget_store.get_widget_mut(widget_id).unwrap().set_size(200, 100);
+    ///     widget_store.update_widget_sizes();
+    ///     widget_store.update_widget_sizes();
+    ///
+    ///     assert_eq!(*resizes.borrow(), vec![(200, 100)]);
+    /// # }
+    /// ```
+    pub fn update_widget_sizes(&mut self) {
+        for container in self.widgets.iter_mut() {
+            let size = container.widget.get_size();
+
+            if size != container.size {
+                let old_size = std::mem::replace(&mut container.size, size.clone());
+
+                container
+                    .widget
+                    .on_resized(container.widget_id, &old_size, &size);
+            }
+        }
+    }
+
+    /// Calls `build` for a newly added widget, so that composite widgets can add their children.
+    /// The widget is swapped out of the store while it builds, as it needs mutable access to the
+    /// store.
+    fn build_widget(&mut self, widget_id: i32) {
+        let placeholder = BuildingWidget::new(self.widgets[widget_id as usize].widget.as_mut());
+        let mut widget = std::mem::replace(
+            &mut self.widgets[widget_id as usize].widget,
+            Box::new(placeholder),
+        );
+
+        let first_built_id = self.widgets.len();
+
+        widget.build(widget_id, self);
+        self.widgets[widget_id as usize].widget = widget;
+
+        for container in self.widgets[first_built_id..].iter_mut() {
+            container.built = true;
+        }
+    }
+
+    /// Indicates whether or not the `Widget` specified by its ID was added by the `build`
+    /// function of a composite widget.
+    pub(crate) fn is_built_widget(&self, widget_id: i32) -> bool {
+        self.widgets[widget_id as usize].built
+    }
+
+    /// Writes the widgets in this store to a layout file, which can be loaded with
+    /// `load_layout`.  See `to_layout_string` for the details of what is saved.  Returns the
+    /// configuration values that could not be written to the file, by widget ID and key: values
+    /// with a custom `ConfigKey` are only saved when the widget writes them itself, through
+    /// `Widget::get_layout_properties`.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     const CONFIG_SPEED: ConfigKey<u32> = ConfigKey::new("myapp.speed", || 1);
+    ///
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let widget_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///
+    ///     widget_store.get_widget_mut(widget_id).unwrap().config().set(CONFIG_SPEED, 5);
+    ///
+    ///     let path = std::env::temp_dir().join("pushrod-save-example.json");
+    ///     let unsaved = widget_store.save(&path).unwrap();
+    ///
+    ///     assert_eq!(unsaved, vec![(widget_id, CONFIG_SPEED.id())]);
+    /// #   std::fs::remove_file(&path).unwrap();
+    /// # }
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<(i32, ConfigKeyId)>> {
+        let mut unsaved = Vec::new();
+
+        fs::write(path, save_layout(self, &mut unsaved).to_string())?;
+
+        Ok(unsaved)
+    }
+
+    /// Converts the widgets in this store to a layout, in the format read by `parse_layout`.
+    /// Every widget is saved with its type, its children, the configuration values that were
+    /// set on the widget (but not theme or inherited values), and the values returned by its
+    /// `get_layout_properties` function.  Widgets added by the `build` function of a composite
+    /// widget are not saved, as they are built again when the layout is loaded.  Values with a
+    /// custom `ConfigKey` are left out, unless the widget writes them; `save` reports them.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::box_widget::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::layout::*;
+    /// # use pushrod::widget::registry::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # use pushrod::widget::widget::*;
+    /// # use pushrod::widgets;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///
+    ///     widget_store.add_widgets(widgets![
+    ///         BoxWidget::builder().name("panel").origin(10, 20).size(200, 100)
+    ///             .color([0.75, 0.75, 1.0, 1.0]).border([0.1, 0.2, 0.3, 1.0], 2) => [
+    ///             BaseWidget::builder().class("button").class("ok").visible(false),
+    ///         ],
+    ///         TimerWidget::builder().timeout(250),
+    ///     ], 0).unwrap();
+    ///
+    ///     // Boxes do not accept padding in a layout, so it is not saved.
+    ///     widget_store.get_widget_mut(1).unwrap().config().set(CONFIG_PADDING, 4);
+    ///
+    ///     let layout = widget_store.to_layout_string();
+    ///
+    ///     assert!(layout.contains(r#""origin": [10, 20]"#));
+    ///     assert!(layout.contains(r#""classes": ["button", "ok"]"#));
+    ///     assert!(!layout.contains("padding"));
+    ///
+    ///     let mut loaded_store = WidgetStore::new();
+    ///     let ids = parse_layout(&mut loaded_store, &WidgetRegistry::new(), &layout).unwrap();
+    ///
+    ///     assert_eq!(loaded_store.to_layout_string(), layout);
+    ///     assert_eq!(loaded_store.get_as::<BoxWidget>(ids["panel"]).unwrap().get_border_thickness(), 2);
+    ///     assert_eq!(loaded_store.get_as::<TimerWidget>(3).unwrap().state().timeout, 250);
+    /// # }
+    /// ```
+    pub fn to_layout_string(&self) -> String {
+        save_layout(self, &mut Vec::new()).to_string()
+    }
+
+    /// Moves the `Widget` specified by its ID to the specified position, moving all of its
+    /// descendants by the same distance, so that composite widgets move as one unit.  Returns a
+    /// `WidgetStoreError::InvalidWidget` error if the widget does not exist, or has been removed.
+    pub fn move_widget(&mut self, widget_id: i32, x: i32, y: i32) -> Result<(), WidgetStoreError> {
+        if !self.is_existing_widget(widget_id) {
+            return Err(WidgetStoreError::InvalidWidget(widget_id));
+        }
+
+        let origin = self.widgets[widget_id as usize].widget.get_origin();
+        let (dx, dy) = (x - origin.x, y - origin.y);
+        let mut widget_ids = vec![widget_id];
+
+        widget_ids.extend(self.get_descendants_of(widget_id));
 
-        widget_size
+        for id in widget_ids {
+            let widget = &mut self.widgets[id as usize].widget;
+            let origin = widget.get_origin();
+
+            widget.set_origin(origin.x + dx, origin.y + dy);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the `Widget` specified by its ID, along with all of its descendants, and their
+    /// bindings, calling their `on_removed` functions.  The IDs of the other widgets do not
+    /// change, and the IDs of removed widgets are not reused.  The base widget (`id = 0`) cannot
+    /// be removed.
+    pub fn remove_widget(&mut self, widget_id: i32) {
+        self.take_widget(widget_id);
+    }
+
+    /// Removes the `Widget` specified by its ID, along with all of its descendants, in the same
+    /// way as `remove_widget`, returning the removed widgets so that they can be put back in
+    /// place with `restore_widget`.  Returns `None` if the widget does not exist, or is the base
+    /// widget.  Bindings to the removed widgets are not restored.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let panel_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let child_id = widget_store
+    ///         .add_widget_to_parent(Box::new(BaseWidget::new()), panel_id)
+    ///         .unwrap();
+    ///     let detached = widget_store.take_widget(panel_id).unwrap();
+    ///
+    ///     assert!(widget_store.get_descendants_of(0).is_empty());
+    ///
+    ///     widget_store.restore_widget(detached).unwrap();
+    ///     assert_eq!(widget_store.get_descendants_of(0), vec![panel_id, child_id]);
+    /// # }
+    /// ```
+    pub fn take_widget(&mut self, widget_id: i32) -> Option<DetachedWidget> {
+        if widget_id <= 0 || !self.is_existing_widget(widget_id) {
+            return None;
+        }
+
+        let parent_id = self.get_parent_of(widget_id);
+        let mut widget_ids = vec![widget_id];
+        let mut detached = DetachedWidget {
+            widgets: Vec::new(),
+        };
+
+        widget_ids.extend(self.get_descendants_of(widget_id));
+
+        // Descendants are removed before their parents.
+        for id in widget_ids.into_iter().rev() {
+            self.widgets[id as usize].widget.on_removed(id);
+
+            let binding_ids: Vec<BindingId> = self
+                .bindings
+                .iter()
+                .filter(|x| x.1 == id)
+                .map(|x| x.0)
+                .collect();
+
+            for binding_id in binding_ids {
+                self.unbind(binding_id);
+            }
+
+            if self.hovered_id == id {
+                self.hovered_id = -1;
+            }
+
+            if self.pressed_id == id {
+                self.pressed_id = -1;
+            }
+
+            if self.focused_id == id {
+                self.focused_id = -1;
+            }
+
+            let container = &mut self.widgets[id as usize];
+            let mut widget =
+                std::mem::replace(&mut container.widget, Box::new(RemovedWidget::new()));
+
+            widget.config().set_locked(CONFIG_NAME.id(), false);
+            container.removed = true;
+
+            detached
+                .widgets
+                .push((id, container.parent_id, container.built, widget));
+            container.parent_id = id;
+            container.style_target = None;
+        }
+
+        self.widgets[parent_id as usize].widget.invalidate();
+        detached.widgets.reverse();
+
+        Some(detached)
+    }
+
+    /// Puts widgets that were removed with `take_widget` back into the store, with the same IDs
+    /// and parents, calling their `on_added` functions.  Returns an error if the widgets cannot
+    /// be restored, as described by `check_restore_widget`, in which case they are dropped.
+    pub fn restore_widget(&mut self, mut detached: DetachedWidget) -> Result<(), WidgetStoreError> {
+        self.check_restore_widget(&mut detached)?;
+
+        let parent_id = detached.widgets[0].1;
+
+        for (id, parent_id, built, mut widget) in detached.widgets {
+            let container = &mut self.widgets[id as usize];
+
+            widget.config().set_locked(CONFIG_NAME.id(), true);
+
+            container.widget = widget;
+            container.parent_id = parent_id;
+            container.built = built;
+            container.removed = false;
+
+            self.apply_theme(id);
+            self.inherit_values(id);
+            self.widgets[id as usize]
+                .widget
+                .on_scheduler_attached(&self.scheduler);
+            self.widgets[id as usize].widget.on_added(id);
+            self.widgets[id as usize].widget.invalidate();
+        }
+
+        self.widgets[parent_id as usize].widget.invalidate();
+
+        Ok(())
+    }
+
+    /// Checks that widgets removed with `take_widget` can be restored with `restore_widget`.
+    /// Returns a `WidgetStoreError::InvalidParent` error if the parent of the widgets no longer
+    /// exists, or a `WidgetStoreError::DuplicateName` error if one of their names has been used
+    /// by another widget since they were removed.
+    pub fn check_restore_widget(
+        &mut self,
+        detached: &mut DetachedWidget,
+    ) -> Result<(), WidgetStoreError> {
+        let parent_id = detached.widgets[0].1;
+
+        if !self.is_existing_widget(parent_id) {
+            return Err(WidgetStoreError::InvalidParent(parent_id));
+        }
+
+        for (_, _, _, widget) in detached.widgets.iter_mut() {
+            if let Some(name) = widget.get_name() {
+                if self.find_by_name(&name).is_some() {
+                    return Err(WidgetStoreError::DuplicateName(name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the name of the `Widget` specified by its ID, returning a
+    /// `WidgetStoreError::DuplicateName` error if another widget already has the name, or a
+    /// `WidgetStoreError::InvalidWidget` error if the widget does not exist.  An empty name
+    /// removes the widget's name.  The widget's theme is applied for its new name.
+    ///
+    /// This is the only way to rename a widget in the store: its name is locked, so renaming it
+    /// with `Widget::set_name`, or by setting `CONFIG_NAME`, has no effect.
+    pub fn set_widget_name(&mut self, widget_id: i32, name: &str) -> Result<(), WidgetStoreError> {
+        if !self.is_existing_widget(widget_id) {
+            return Err(WidgetStoreError::InvalidWidget(widget_id));
+        }
+
+        if let Some(existing_id) = self.find_by_name(name) {
+            if existing_id != widget_id {
+                return Err(WidgetStoreError::DuplicateName(name.to_string()));
+            }
+        }
+
+        let config = self.widgets[widget_id as usize].widget.config();
+
+        config.set_locked(CONFIG_NAME.id(), false);
+        config.set(CONFIG_NAME, name.to_string());
+        config.set_locked(CONFIG_NAME.id(), true);
+        self.apply_theme(widget_id);
+
+        Ok(())
+    }
+
+    /// Retrieves the ID of the `Widget` with the specified name, or `None` if there is none.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let mut ok_button = BaseWidget::new();
+    ///     let mut cancel_button = BaseWidget::new();
+    ///
+    ///     ok_button.set_name("ok");
+    ///     ok_button.add_class("button");
+    ///     cancel_button.set_name("ok");
+    ///     cancel_button.add_class("button");
+    ///
+    ///     let ok_id = widget_store.add_widget(Box::new(ok_button)).unwrap();
+    ///
+    ///     assert_eq!(widget_store.add_widget(Box::new(cancel_button)),
+    ///         Err(WidgetStoreError::DuplicateName("ok".to_string())));
+    ///
+    ///     let mut cancel_button = BaseWidget::new();
+    ///
+    ///     cancel_button.add_class("button");
+    ///
+    ///     let cancel_id = widget_store.add_widget(Box::new(cancel_button)).unwrap();
+    ///
+    ///     assert!(widget_store.set_widget_name(cancel_id, "ok").is_err());
+    ///     assert!(widget_store.set_widget_name(cancel_id, "cancel").is_ok());
+    ///     assert_eq!(widget_store.find_by_name("ok"), Some(ok_id));
+    ///     assert_eq!(widget_store.find_by_name("cancel"), Some(cancel_id));
+    ///     assert_eq!(widget_store.find_by_class("button"), vec![ok_id, cancel_id]);
+    ///     assert_eq!(widget_store.find_by_type("base"), vec![0, ok_id, cancel_id]);
+    /// # }
+    /// ```
+    pub fn find_by_name(&mut self, name: &str) -> Option<i32> {
+        if name.is_empty() {
+            return None;
+        }
+
+        self.widgets.iter_mut().find_map(|x| {
+            Some(x.widget_id).filter(|_| x.widget.get_name().map_or(false, |x| x == name))
+        })
+    }
+
+    /// Retrieves the IDs of all widgets that have the specified class, in order of ID.
+    pub fn find_by_class(&mut self, class: &str) -> Vec<i32> {
+        self.widgets
+            .iter_mut()
+            .filter_map(|x| Some(x.widget_id).filter(|_| x.widget.has_class(class)))
+            .collect()
+    }
+
+    /// Retrieves the IDs of all widgets of the specified type name, as returned by
+    /// `Widget::get_widget_type`, in order of ID.
+    pub fn find_by_type(&mut self, widget_type: &str) -> Vec<i32> {
+        self.widgets
+            .iter()
+            .filter(|x| !x.removed && x.widget.get_widget_type() == widget_type)
+            .map(|x| x.widget_id)
+            .collect()
     }
 
     /// Retrieves the parent of the widget requested.  Parent of 0 or -1 will always return 0.
@@ -131,10 +1000,82 @@ impl WidgetStore {
     /// can be used recursively to determine the widget ownership tree, or the redraw order in which
     /// repaint should take place.
     pub fn get_children_of(&self, parent_id: i32) -> Vec<i32> {
-        self.widgets
+        let mut children: Vec<i32> = self
+            .widgets
             .iter()
             .filter(|x| x.parent_id == parent_id)
             .map(|x| x.widget_id)
+            .collect();
+
+        self.sort_siblings(&mut children);
+        children
+    }
+
+    /// Sorts the IDs of sibling widgets in the order in which they are listed and drawn.
+    fn sort_siblings(&self, widget_ids: &mut [i32]) {
+        widget_ids.sort_by_key(|x| (self.widgets[*x as usize].order, *x));
+    }
+
+    /// Retrieves the position of a widget among its siblings.
+    pub(crate) fn get_sibling_order(&self, widget_id: i32) -> i32 {
+        self.widgets[widget_id as usize].order
+    }
+
+    /// Moves a widget to a position among its siblings, such as the position of a widget that it
+    /// replaces.  Siblings with the same position are ordered by ID.
+    pub(crate) fn set_sibling_order(&mut self, widget_id: i32, order: i32) {
+        self.widgets[widget_id as usize].order = order;
+        self.widgets[widget_id as usize].widget.invalidate();
+    }
+
+    /// Retrieves the IDs of all of the descendants of the `parent_id`, in tree order: each widget
+    /// is followed by its own descendants, before its next sibling.  The children of every
+    /// widget are indexed in a single pass, so this takes time proportional to the number of
+    /// widgets in the store.
+    pub fn get_descendants_of(&self, parent_id: i32) -> Vec<i32> {
+        let mut children: Vec<Vec<i32>> = vec![Vec::new(); self.widgets.len()];
+
+        for container in self.widgets.iter() {
+            if container.parent_id != container.widget_id {
+                children[container.parent_id as usize].push(container.widget_id);
+            }
+        }
+
+        for child_ids in children.iter_mut().filter(|x| x.len() > 1) {
+            self.sort_siblings(child_ids);
+        }
+
+        let mut descendants = Vec::new();
+        let mut stack: Vec<i32> = match children.get(parent_id as usize) {
+            Some(child_ids) => child_ids.iter().rev().cloned().collect(),
+            None => Vec::new(),
+        };
+
+        while let Some(widget_id) = stack.pop() {
+            descendants.push(widget_id);
+            stack.extend(children[widget_id as usize].iter().rev());
+        }
+
+        descendants
+    }
+
+    /// Retrieves the IDs of all widgets matching a `WidgetQuery`, such as
+    /// `#toolbar > .button:enabled`, in tree order.  Returns a `WidgetStoreError::InvalidQuery`
+    /// error if the query cannot be parsed.  See `WidgetQuery` for the query syntax.
+    pub fn query(&mut self, query: &str) -> Result<Vec<i32>, WidgetStoreError> {
+        let query = WidgetQuery::parse(query)?;
+
+        Ok(self.query_with(&query))
+    }
+
+    /// Retrieves the IDs of all widgets matching a parsed `WidgetQuery`, in tree order.
+    pub fn query_with(&mut self, query: &WidgetQuery) -> Vec<i32> {
+        let mut widget_ids = vec![0];
+
+        widget_ids.extend(self.get_descendants_of(0));
+        widget_ids
+            .into_iter()
+            .filter(|x| query.matches(self, *x))
             .collect()
     }
 
@@ -143,7 +1084,13 @@ impl WidgetStore {
     pub fn get_widget_id_for_point(&mut self, point: Point) -> i32 {
         let mut found_id = -1;
 
-        for (pos, obj) in self.widgets.iter_mut().enumerate() {
+        for pos in 0..self.widgets.len() {
+            // Skip over hidden widgets, and widgets with hidden parents.
+            if !self.is_widget_visible(pos as i32) {
+                continue;
+            }
+
+            let obj = &mut self.widgets[pos];
             let widget_point = &obj.widget.get_origin();
             let widget_size: crate::core::point::Size = obj.widget.get_size();
 
@@ -162,11 +1109,98 @@ impl WidgetStore {
         found_id
     }
 
+    /// Indicates whether or not the `Widget` specified by its ID is visible on the screen: the
+    /// widget and all of its parents must be visible.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::point::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let container_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let mut child = BaseWidget::new();
+    ///
+    ///     child.set_origin(10, 10);
+    ///     child.set_size(20, 20);
+    ///
+    ///     let child_id = widget_store
+    ///         .add_widget_to_parent(Box::new(child), container_id)
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(widget_store.get_widget_id_for_point(make_point_i32(15, 15)), child_id);
+    ///
+    ///     widget_store.widgets[container_id as usize].widget.set_visible(false);
+    ///
+    ///     assert!(!widget_store.is_widget_visible(child_id));
+    ///     assert_eq!(widget_store.get_widget_id_for_point(make_point_i32(15, 15)), 0);
+    /// # }
+    /// ```
+    pub fn is_widget_visible(&mut self, widget_id: i32) -> bool {
+        let mut current_id = widget_id;
+
+        loop {
+            if !self.widgets[current_id as usize].widget.is_visible() {
+                return false;
+            }
+
+            let parent_id = self.get_parent_of(current_id);
+
+            if parent_id == current_id {
+                return true;
+            }
+
+            current_id = parent_id;
+        }
+    }
+
+    /// Indicates whether or not the `Widget` specified by its ID is enabled: the widget and all
+    /// of its parents must be enabled.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::theme::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let container_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let child_id = widget_store
+    ///         .add_widget_to_parent(Box::new(BaseWidget::new()), container_id)
+    ///         .unwrap();
+    ///
+    ///     widget_store.widgets[container_id as usize].widget.set_enabled(false);
+    ///     widget_store.set_focused(child_id);
+    ///
+    ///     assert!(!widget_store.is_widget_enabled(child_id));
+    ///     assert_eq!(widget_store.get_widget_state(child_id), WidgetState::Disabled);
+    ///     assert_eq!(widget_store.get_focused_id(), -1);
+    /// # }
+    /// ```
+    pub fn is_widget_enabled(&mut self, widget_id: i32) -> bool {
+        let mut current_id = widget_id;
+
+        loop {
+            if !self.widgets[current_id as usize].widget.is_enabled() {
+                return false;
+            }
+
+            let parent_id = self.get_parent_of(current_id);
+
+            if parent_id == current_id {
+                return true;
+            }
+
+            current_id = parent_id;
+        }
+    }
+
     /// Recursive draw object: paints objects in order of appearance on the screen.  This does not
     /// account for object depth, but it is implied that objects' parents are displayed in stacking
-    /// order.  Therefore, the parent is drawn first, then sibling, and other siblings.  This draw
-    /// function is used by the `Pushrod` main loop, and is meant to be called in a `draw_2d`
-    /// closure.
+    /// order.  Therefore, the parent is drawn first, then sibling, and other siblings.  Hidden
+    /// widgets, and their children, are skipped.  This draw function is used by the `Pushrod`
+    /// main loop, and is meant to be called in a `draw_2d` closure.
     pub fn draw(&mut self, widget_id: i32, c: Context, g: &mut G2d) {
         let parents_of_widget = self.get_children_of(widget_id);
 
@@ -178,6 +1212,10 @@ impl WidgetStore {
             let paint_id = parents_of_widget[pos];
             let paint_widget = &mut self.widgets[paint_id as usize];
 
+            if !paint_widget.widget.is_visible() {
+                continue;
+            }
+
             if &paint_widget.widget.is_invalidated() == &true {
                 // Implementation of auto-clipping.  Clips the object's drawing area.
                 if paint_widget.widget.get_autoclip() {
@@ -228,28 +1266,564 @@ impl WidgetStore {
         }
     }
 
-    /// Callback to `mouse_entered` for a `Widget` by ID.
+    /// Callback to `mouse_entered` for a `Widget` by ID.  Disabled widgets are skipped.
     pub fn mouse_entered_for_id(&mut self, id: i32) {
-        &self.widgets[id as usize].widget.mouse_entered(id);
+        if self.is_widget_enabled(id) {
+            self.widgets[id as usize].widget.mouse_entered(id);
+        }
     }
 
-    /// Callback to `mouse_exited` for a `Widget` by ID.
+    /// Callback to `mouse_exited` for a `Widget` by ID.  Disabled widgets are skipped.
     pub fn mouse_exited_for_id(&mut self, id: i32) {
-        &self.widgets[id as usize].widget.mouse_exited(id);
+        if self.is_widget_enabled(id) {
+            self.widgets[id as usize].widget.mouse_exited(id);
+        }
     }
 
     /// Callback to `mouse_scrolled` for a `Widget` by ID, with the mouse scroll `Point`.
+    /// Disabled widgets are skipped.
     pub fn mouse_scrolled_for_id(&mut self, id: i32, point: Point) {
-        &self.widgets[id as usize].widget.mouse_scrolled(id, point);
+        if self.is_widget_enabled(id) {
+            self.widgets[id as usize].widget.mouse_scrolled(id, point);
+        }
     }
 
     /// Callback to `mouse_moved` for a `Widget` by ID, with the mouse position at `Point`.
+    /// Disabled widgets are skipped.
     pub fn mouse_moved_for_id(&mut self, id: i32, point: Point) {
-        &self.widgets[id as usize].widget.mouse_moved(id, point);
+        if self.is_widget_enabled(id) {
+            self.widgets[id as usize].widget.mouse_moved(id, point);
+        }
+    }
+
+    /// Adds an observer for a configuration key of the `Widget` specified by its ID.  The
+    /// observer is called with the widget ID, the old value and the new value whenever the
+    /// value changes.  Returns the `ObserverId`, which can be removed with `unobserve`.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::point::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let widget_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///
+    ///     widget_store.observe(widget_id, CONFIG_SIZE, Box::new(|widget_id, old: &Size, new: &Size| {
+    ///         eprintln!("Widget {} resized from {}x{} to {}x{}", widget_id, old.w, old.h, new.w, new.h);
+    ///     }));
+    ///
+    ///     widget_store.widgets[widget_id as usize].widget.set_size(100, 50);
+    /// # }
+    /// ```
+    pub fn observe<T: PartialEq + 'static>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        callback: Box<dyn Fn(i32, &T, &T)>,
+    ) -> ObserverId {
+        self.widgets[widget_id as usize]
+            .widget
+            .config()
+            .observe(key, Box::new(move |old, new| callback(widget_id, old, new)))
+    }
+
+    /// Removes an observer from the `Widget` specified by its ID.
+    pub fn unobserve(&mut self, widget_id: i32, observer_id: ObserverId) {
+        self.widgets[widget_id as usize]
+            .widget
+            .config()
+            .unobserve(observer_id);
     }
 
-    /// Retrieves a reference to the `Box`ed `Widget` object by its ID.
+    fn add_binding(&mut self, widget_id: i32, binding: Box<dyn Binding>) -> BindingId {
+        let id = self.next_binding_id;
+
+        binding.apply(self.widgets[widget_id as usize].widget.as_mut());
+        self.next_binding_id += 1;
+        self.bindings.push((id, widget_id, binding));
+
+        id
+    }
+
+    /// Binds a `Property` to a configuration value of the `Widget` specified by its ID.  The
+    /// widget's value is set immediately, and is updated during the update phase whenever the
+    /// property changes.  Returns the `BindingId`, which can be removed with `unbind`.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::property::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let widget_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let color = Property::new([1.0, 0.0, 0.0, 1.0]);
+    ///     let clicks = Property::new(0);
+    ///
+    ///     widget_store.bind(widget_id, CONFIG_COLOR, &color);
+    ///     widget_store.bind_with(widget_id, CONFIG_TEXT, &clicks,
+    ///         Box::new(|clicks| format!("Clicked {} times", clicks)));
+    ///
+    ///     color.set([0.0, 1.0, 0.0, 1.0]);
+    ///     clicks.set(3);
+    ///     widget_store.update_bindings();
+    ///
+    ///     let widget = &mut widget_store.widgets[widget_id as usize].widget;
+    ///     assert_eq!(widget.get_color(), [0.0, 1.0, 0.0, 1.0]);
+    ///     assert_eq!(widget.config().get(CONFIG_TEXT), "Clicked 3 times");
+    /// # }
+    /// ```
+    pub fn bind<T: Clone + PartialEq + 'static>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        property: &Property<T>,
+    ) -> BindingId {
+        self.bind_with(widget_id, key, property, Box::new(|value: &T| value.clone()))
+    }
+
+    /// Binds a `Property` to a configuration value of the `Widget` specified by its ID, in the
+    /// same way as `bind`, using a converter to format the property's value for the widget.
+    pub fn bind_with<T: Clone + PartialEq + 'static, U: 'static>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<U>,
+        property: &Property<T>,
+        to_widget: Box<dyn Fn(&T) -> U>,
+    ) -> BindingId {
+        let binding = PropertyBinding::new(property, key, to_widget);
+
+        self.add_binding(widget_id, Box::new(binding))
+    }
+
+    /// Binds a `Property` to a configuration value of the `Widget` specified by its ID in both
+    /// directions: changes to the property update the widget, and changes to the widget's value
+    /// are copied back into the property immediately.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::property::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let widget_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let visible_width = Property::new(100);
+    ///
+    ///     widget_store.bind_two_way(widget_id, CONFIG_BORDER_WIDTH, &visible_width);
+    ///     widget_store.widgets[widget_id as usize].widget.config().set(CONFIG_BORDER_WIDTH, 5);
+    ///
+    ///     assert_eq!(visible_width.get(), 5);
+    /// # }
+    /// ```
+    pub fn bind_two_way<T: Clone + PartialEq + 'static>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<T>,
+        property: &Property<T>,
+    ) -> BindingId {
+        self.bind_two_way_with(
+            widget_id,
+            key,
+            property,
+            Box::new(|value: &T| value.clone()),
+            Box::new(|value: &T| value.clone()),
+        )
+    }
+
+    /// Binds a `Property` to a configuration value of the `Widget` specified by its ID in both
+    /// directions, in the same way as `bind_two_way`, using a pair of converters between the
+    /// property's value and the widget's value.
+    pub fn bind_two_way_with<T: Clone + PartialEq + 'static, U: PartialEq + 'static>(
+        &mut self,
+        widget_id: i32,
+        key: ConfigKey<U>,
+        property: &Property<T>,
+        to_widget: Box<dyn Fn(&T) -> U>,
+        from_widget: Box<dyn Fn(&U) -> T>,
+    ) -> BindingId {
+        let mut binding = PropertyBinding::new(property, key, to_widget);
+
+        binding.observe_widget(
+            self.widgets[widget_id as usize].widget.as_mut(),
+            from_widget,
+        );
+
+        self.add_binding(widget_id, Box::new(binding))
+    }
+
+    /// Removes a binding by its `BindingId`.  The widget keeps its current value.
+    pub fn unbind(&mut self, binding_id: BindingId) {
+        if let Some(position) = self.bindings.iter().position(|x| x.0 == binding_id) {
+            let (_, widget_id, binding) = self.bindings.remove(position);
+
+            if let Some(observer_id) = binding.observer_id() {
+                self.unobserve(widget_id, observer_id);
+            }
+        }
+    }
+
+    /// Applies the values of all bound `Property` objects that have changed to their widgets,
+    /// invalidating those widgets.  This is called by the `Pushrod` run loop during the update
+    /// phase, before widgets are updated.
+    pub fn update_bindings(&mut self) {
+        for (_, widget_id, binding) in self.bindings.iter() {
+            binding.apply(self.widgets[*widget_id as usize].widget.as_mut());
+        }
+    }
+
+    /// Sets the `Theme` that provides default configuration values for all widgets, applying it
+    /// to every widget in the store, and invalidating them.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+
+        for widget_id in 0..self.widgets.len() as i32 {
+            self.apply_theme(widget_id);
+        }
+    }
+
+    /// Retrieves the current `Theme`.
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Retrieves the `WidgetState` of the `Widget` specified by its ID, which is used to select
+    /// its theme values.  A disabled widget is always in the `Disabled` state.  Otherwise, a
+    /// pressed widget takes precedence over a hovered widget, which takes precedence over a
+    /// focused widget.
+    pub fn get_widget_state(&mut self, widget_id: i32) -> WidgetState {
+        if !self.is_widget_enabled(widget_id) {
+            WidgetState::Disabled
+        } else if widget_id == self.pressed_id {
+            WidgetState::Pressed
+        } else if widget_id == self.hovered_id {
+            WidgetState::Hover
+        } else if widget_id == self.focused_id {
+            WidgetState::Focused
+        } else {
+            WidgetState::Normal
+        }
+    }
+
+    /// Retrieves the ID of the `Widget` that the mouse is over, or -1 if there is none.
+    pub fn get_hovered_id(&self) -> i32 {
+        self.hovered_id
+    }
+
+    /// Retrieves the ID of the `Widget` that a mouse button was pressed on, or -1 if there is
+    /// none.
+    pub fn get_pressed_id(&self) -> i32 {
+        self.pressed_id
+    }
+
+    /// Retrieves the ID of the `Widget` that has focus, or -1 if there is none.
+    pub fn get_focused_id(&self) -> i32 {
+        self.focused_id
+    }
+
+    /// Sends a mouse exit to the hovered `Widget` if it, or one of its parents, has been hidden
+    /// or disabled, so that it no longer appears as hovered.  Pressed and focused widgets that
+    /// have been disabled lose their state.  This is called by the `Pushrod` run loop during the
+    /// update phase.
+    pub fn update_hovered(&mut self) {
+        let hovered_id = self.hovered_id;
+
+        if hovered_id != -1
+            && (!self.is_widget_visible(hovered_id) || !self.is_widget_enabled(hovered_id))
+        {
+            self.exit_hovered();
+        }
+
+        if self.pressed_id != -1 && !self.is_widget_enabled(self.pressed_id) {
+            self.set_pressed(-1);
+        }
+
+        if self.focused_id != -1 && !self.is_widget_enabled(self.focused_id) {
+            self.set_focused(-1);
+        }
+    }
+
+    /// Re-applies the theme to widgets whose `WidgetState`, name or classes have changed since
+    /// their theme was last applied, such as widgets that have been enabled or disabled,
+    /// invalidating them.  This is called by the `Pushrod` run loop during the update phase.
+    ///
+    /// Names must be unique, so renaming a widget in the store other than with
+    /// `set_widget_name` has no effect.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let mut ok_button = BaseWidget::new();
+    ///     let mut cancel_button = BaseWidget::new();
+    ///
+    ///     ok_button.set_name("ok");
+    ///     cancel_button.set_name("cancel");
+    ///
+    ///     let ok_id = widget_store.add_widget(Box::new(ok_button)).unwrap();
+    ///     let cancel_id = widget_store.add_widget(Box::new(cancel_button)).unwrap();
+    ///
+    ///     widget_store.get_widget_mut(cancel_id).unwrap().set_name("ok");
+    ///     widget_store.update_widget_states();
+    ///
+    ///     assert_eq!(widget_store.find_by_name("ok"), Some(ok_id));
+    ///     assert_eq!(widget_store.find_by_name("cancel"), Some(cancel_id));
+    /// # }
+    /// ```
+    pub fn update_widget_states(&mut self) {
+        for widget_id in 0..self.widgets.len() as i32 {
+            let style_target = Some(self.get_style_target(widget_id));
+
+            if style_target != self.widgets[widget_id as usize].style_target {
+                self.apply_theme(widget_id);
+            }
+        }
+    }
+
+    /// Sends a mouse exit to the hovered `Widget`, if there is one, and clears the hovered
+    /// widget.  Only enabled widgets can become hovered, so the widget has received a mouse
+    /// enter, and the exit is sent even if it has since been disabled.
+    pub fn exit_hovered(&mut self) {
+        let hovered_id = self.hovered_id;
+
+        if hovered_id != -1 {
+            self.widgets[hovered_id as usize]
+                .widget
+                .mouse_exited(hovered_id);
+            self.set_hovered(-1);
+        }
+    }
+
+    /// Sets the `Widget` that the mouse is over, by its ID, or -1 for none.  Disabled widgets
+    /// cannot be hovered.  This is called by the `Pushrod` run loop as the mouse moves between
+    /// widgets.
+    pub fn set_hovered(&mut self, widget_id: i32) {
+        if widget_id != -1 && !self.is_widget_enabled(widget_id) {
+            return;
+        }
+
+        let previous_id = self.hovered_id;
+
+        self.hovered_id = widget_id;
+        self.state_changed(previous_id, widget_id);
+    }
+
+    /// Sets the `Widget` that a mouse button was pressed on, by its ID, or -1 when the button
+    /// is released.  Disabled widgets cannot be pressed.  This is called by the `Pushrod` run
+    /// loop.
+    pub fn set_pressed(&mut self, widget_id: i32) {
+        if widget_id != -1 && !self.is_widget_enabled(widget_id) {
+            return;
+        }
+
+        let previous_id = self.pressed_id;
+
+        self.pressed_id = widget_id;
+        self.state_changed(previous_id, widget_id);
+    }
+
+    /// Sets the `Widget` that has focus, by its ID, or -1 for none.  Disabled widgets cannot take
+    /// focus.
+    pub fn set_focused(&mut self, widget_id: i32) {
+        if widget_id != -1 && !self.is_widget_enabled(widget_id) {
+            return;
+        }
+
+        let previous_id = self.focused_id;
+
+        self.focused_id = widget_id;
+        self.state_changed(previous_id, widget_id);
+    }
+
+    /// Re-applies the theme to two widgets whose state has changed.
+    fn state_changed(&mut self, previous_id: i32, widget_id: i32) {
+        if previous_id != widget_id {
+            self.apply_theme(previous_id);
+            self.apply_theme(widget_id);
+        }
+    }
+
+    /// Builds the `StyleTarget` used to select theme values for the `Widget` specified by its ID.
+    fn get_style_target(&mut self, widget_id: i32) -> StyleTarget {
+        let state = self.get_widget_state(widget_id);
+        let widget = &mut self.widgets[widget_id as usize].widget;
+
+        StyleTarget {
+            widget_type: widget.get_widget_type().to_string(),
+            name: widget.get_name(),
+            classes: widget.get_classes(),
+            state,
+        }
+    }
+
+    /// Resolves the theme values for the `Widget` specified by its ID from its type, name,
+    /// classes and state, and stores them in its configuration.  IDs that do not exist are
+    /// ignored.
+    fn apply_theme(&mut self, widget_id: i32) {
+        if widget_id < 0 || widget_id as usize >= self.widgets.len() {
+            return;
+        }
+
+        let target = self.get_style_target(widget_id);
+        let values = self.theme.resolve(&target);
+        let container = &mut self.widgets[widget_id as usize];
+
+        container.style_target = Some(target);
+
+        for key_id in container.widget.config().set_theme_values(values) {
+            container.widget.on_config_changed(key_id);
+        }
+
+        container.widget.invalidate();
+    }
+
+    /// Resolves the values of inherited configuration keys, such as `CONFIG_TEXT_COLOR`, for all
+    /// widgets: a widget that has no value stored for an inherited key, explicitly or by its
+    /// theme, uses the value of its nearest parent that does.  Widgets whose inherited values
+    /// have changed are invalidated.  This is called by the `Pushrod` run loop during the update
+    /// phase, after bindings and animations have been applied.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let container_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///     let child_id = widget_store
+    ///         .add_widget_to_parent(Box::new(BaseWidget::new()), container_id)
+    ///         .unwrap();
+    ///
+    ///     widget_store.widgets[container_id as usize].widget.config()
+    ///         .set(CONFIG_TEXT_COLOR, [1.0, 0.0, 0.0, 1.0]);
+    ///     widget_store.update_inherited_values();
+    ///
+    ///     let child = &mut widget_store.widgets[child_id as usize].widget;
+    ///     assert_eq!(child.config().get(CONFIG_TEXT_COLOR), [1.0, 0.0, 0.0, 1.0]);
+    ///     assert!(child.is_invalidated());
+    /// # }
+    /// ```
+    pub fn update_inherited_values(&mut self) {
+        // Parents are visited before their children, so a single pass in tree order passes
+        // values down the entire tree, in time proportional to the number of widgets.
+        for widget_id in self.get_descendants_of(0) {
+            self.inherit_values(widget_id);
+        }
+    }
+
+    /// Copies the inheritable values of a widget's parent into the widget, invalidating the
+    /// widget if they have changed.
+    fn inherit_values(&mut self, widget_id: i32) {
+        let parent_id = self.get_parent_of(widget_id);
+
+        if parent_id == widget_id {
+            return;
+        }
+
+        let values = self.widgets[parent_id as usize]
+            .widget
+            .config()
+            .get_inheritable_values();
+        let widget = &mut self.widgets[widget_id as usize].widget;
+        let changed = widget.config().set_inherited_values(values);
+
+        for key_id in changed.iter() {
+            widget.on_config_changed(*key_id);
+        }
+
+        if !changed.is_empty() {
+            widget.invalidate();
+        }
+    }
+
+    /// Retrieves a reference to the `Box`ed `Widget` object by its ID.  Use `get_widget_mut` to
+    /// modify the widget, or `get_as_mut` to access it as its concrete type.
     pub fn get_widget_for_id(&mut self, id: i32) -> &Box<dyn Widget> {
         &self.widgets[id as usize].widget
     }
+
+    /// Retrieves a mutable reference to the `Widget` object by its ID.  Returns `None` if the
+    /// widget does not exist, or has been removed.
+    pub fn get_widget_mut(&mut self, id: i32) -> Option<&mut dyn Widget> {
+        if !self.is_existing_widget(id) {
+            return None;
+        }
+
+        match self.widgets.get_mut(id as usize) {
+            Some(container) => Some(container.widget.as_mut()),
+            None => None,
+        }
+    }
+
+    /// Retrieves a reference to the `Widget` specified by its ID as its concrete type `T`.
+    /// Returns `None` if the widget does not exist, has been removed, or is not a `T`.
+    pub fn get_as<T: Widget + 'static>(&self, id: i32) -> Option<&T> {
+        if !self.is_existing_widget(id) {
+            return None;
+        }
+
+        self.widgets
+            .get(id as usize)
+            .and_then(|x| x.widget.as_ref().as_any().downcast_ref::<T>())
+    }
+
+    /// Retrieves a mutable reference to the `Widget` specified by its ID as its concrete type
+    /// `T`, so that methods of the concrete type can be called after the widget has been added.
+    /// Returns `None` if the widget does not exist, has been removed, or is not a `T`.
+    ///
+    /// Example:
+    /// ```
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::box_widget::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # use pushrod::widget::widget::*;
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let mut status_box = BoxWidget::new();
+    ///
+    ///     status_box.set_name("status");
+    ///
+    ///     let box_id = widget_store.add_widget(Box::new(status_box)).unwrap();
+    ///
+    ///     widget_store.get_as_mut::<BoxWidget>(box_id).unwrap().set_border_thickness(3);
+    ///     widget_store.get_widget_mut(box_id).unwrap().set_color([1.0, 0.0, 0.0, 1.0]);
+    ///
+    ///     assert!(widget_store.get_as_mut::<TimerWidget>(box_id).is_none());
+    ///
+    ///     let status_box = widget_store.get_by_name_as_mut::<BoxWidget>("status").unwrap();
+    ///
+    ///     assert_eq!(status_box.get_border_thickness(), 3);
+    ///     assert_eq!(status_box.get_color(), [1.0, 0.0, 0.0, 1.0]);
+    /// # }
+    /// ```
+    pub fn get_as_mut<T: Widget + 'static>(&mut self, id: i32) -> Option<&mut T> {
+        if !self.is_existing_widget(id) {
+            return None;
+        }
+
+        self.widgets
+            .get_mut(id as usize)
+            .and_then(|x| x.widget.as_mut().as_any_mut().downcast_mut::<T>())
+    }
+
+    /// Retrieves the typed state of the `StatefulWidget` specified by its ID.  Returns `None` if
+    /// the widget does not exist, has been removed, or is not a `W`.
+    pub fn get_state<W: StatefulWidget + 'static>(&self, id: i32) -> Option<W::State> {
+        self.get_as::<W>(id).map(|x| x.state())
+    }
+
+    /// Retrieves a mutable reference to the `Widget` with the specified name as its concrete
+    /// type `T`.  Returns `None` if there is no widget with the name, or if it is not a `T`.
+    pub fn get_by_name_as_mut<T: Widget + 'static>(&mut self, name: &str) -> Option<&mut T> {
+        let id = self.find_by_name(name)?;
+
+        self.get_as_mut::<T>(id)
+    }
 }
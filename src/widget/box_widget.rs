@@ -17,15 +17,28 @@ use piston_window::*;
 
 use crate::core::callbacks::*;
 use crate::core::point::*;
+use crate::widget::builder::*;
 use crate::widget::config::*;
 use crate::widget::widget::*;
 
+/// The typed state of a `BoxWidget`.  Its values are also stored in the widget's configuration,
+/// under the keys listed below.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BoxState {
+    /// The color of the border, stored as `CONFIG_COLOR_BORDER`.
+    pub border_color: types::Color,
+
+    /// The thickness of the border, stored as `CONFIG_BORDER_WIDTH`.
+    pub border_thickness: u8,
+}
+
 /// This is the `BoxWidget`, which contains a top-level widget for display, overriding the
 /// draw method to draw the base widget and the border for this box.
 pub struct BoxWidget {
     config: Configurable,
     callbacks: CallbackStore,
     base_widget: BaseWidget,
+    state: BoxState,
 }
 
 /// Implementation of the constructor for the `BaseWidget`.  Creates a new base widget
@@ -36,39 +49,55 @@ impl BoxWidget {
             config: Configurable::new(),
             callbacks: CallbackStore::new(),
             base_widget: BaseWidget::new(),
+            state: BoxState {
+                border_color: CONFIG_COLOR_BORDER.default_value(),
+                border_thickness: CONFIG_BORDER_WIDTH.default_value(),
+            },
         }
     }
 
+    /// Creates a `WidgetBuilder` for a new `BoxWidget`.
+    pub fn builder() -> WidgetBuilder<Self> {
+        WidgetBuilder::new(Self::new())
+    }
+
+    /// Copies the origin, size and color into the base widget before it is drawn.  Values can
+    /// also come from the theme, inherited values, bindings and animations, which do not go
+    /// through the setters.
+    fn sync_base_widget(&mut self) {
+        let origin: Point = self.config.get(CONFIG_ORIGIN);
+        let size: crate::core::point::Size = self.config.get(CONFIG_SIZE);
+        let color: types::Color = self.config.get(CONFIG_COLOR);
+
+        self.base_widget.set_origin(origin.x, origin.y);
+        self.base_widget.set_size(size.w, size.h);
+        self.base_widget.set_color(color);
+    }
+
     /// Sets the border color for this widget.
     pub fn set_border_color(&mut self, color: types::Color) {
-        self.config()
-            .set(CONFIG_COLOR_BORDER, WidgetConfig::BorderColor { color });
+        self.config().set(CONFIG_COLOR_BORDER, color);
+        self.state.border_color = color;
         self.invalidate();
     }
 
     /// Retrieves the border color of this widget.
     /// Defaults to black color `[0.0, 0.0, 0.0, 1.0]` if not set.
-    pub fn get_border_color(&mut self) -> types::Color {
-        match self.config().get(CONFIG_COLOR_BORDER) {
-            Some(WidgetConfig::BorderColor { color }) => [color[0], color[1], color[2], color[3]],
-            _ => [0.0, 0.0, 0.0, 1.0],
-        }
+    pub fn get_border_color(&self) -> types::Color {
+        self.state.border_color
     }
 
     /// Sets the thickness of the border for this widget.
     pub fn set_border_thickness(&mut self, thickness: u8) {
-        self.config()
-            .set(CONFIG_BORDER_WIDTH, WidgetConfig::BorderWidth { thickness });
+        self.config().set(CONFIG_BORDER_WIDTH, thickness);
+        self.state.border_thickness = thickness;
         self.invalidate();
     }
 
     /// Retrieves the border thickness of this widget.
     /// Defaults to 1 if not set.
-    pub fn get_border_thickness(&mut self) -> u8 {
-        match self.config().get(CONFIG_BORDER_WIDTH) {
-            Some(WidgetConfig::BorderWidth { ref thickness }) => thickness.clone(),
-            _ => 1,
-        }
+    pub fn get_border_thickness(&self) -> u8 {
+        self.state.border_thickness
     }
 
     /// Helper function that sets both the color of the border and the thickness at the same time.
@@ -176,47 +205,50 @@ impl Widget for BoxWidget {
         &mut self.config
     }
 
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
     fn callbacks(&mut self) -> &mut CallbackStore {
         &mut self.callbacks
     }
 
+    fn get_widget_type(&self) -> &'static str {
+        "box"
+    }
+
     /// Sets the `Point` of origin for this widget and the base widget, given the X and Y
     /// coordinates.  Invalidates the widget afterward.
     fn set_origin(&mut self, x: i32, y: i32) {
-        self.config().set(
-            CONFIG_ORIGIN,
-            WidgetConfig::Origin {
-                point: Point { x, y },
-            },
-        );
+        self.config().set(CONFIG_ORIGIN, Point { x, y });
         self.base_widget.set_origin(x, y);
         self.invalidate();
     }
 
-    /// Sets the `Size` for this widget and the base widget, given width and height.  Invalidates the widget afterward.
+    /// Sets the `Size` for this widget and the base widget, given width and height.  Invalidates
+    /// the widget afterward.
     fn set_size(&mut self, w: i32, h: i32) {
-        self.config().set(
-            CONFIG_SIZE,
-            WidgetConfig::Size {
-                size: crate::core::point::Size { w, h },
-            },
-        );
+        self.config()
+            .set(CONFIG_SIZE, crate::core::point::Size { w, h });
         self.base_widget.set_size(w, h);
         self.invalidate();
     }
 
     /// Sets the color for this widget.  Invalidates the widget afterward.
     fn set_color(&mut self, color: types::Color) {
-        self.config()
-            .set(CONFIG_COLOR, WidgetConfig::Color { color });
+        self.config().set(CONFIG_COLOR, color);
         self.base_widget.set_color(color);
         self.invalidate();
     }
 
-    /// Retrieves the color of this widget.
-    /// Defaults to white color `[1.0; 4]` if not set.
-    fn get_color(&mut self) -> types::Color {
-        self.base_widget.get_color()
+    /// Updates the border color and thickness when they are changed by a theme, a binding, an
+    /// animation or a layout.
+    fn on_config_changed(&mut self, key_id: ConfigKeyId) {
+        if key_id == CONFIG_COLOR_BORDER.id() {
+            self.state.border_color = self.config.get(CONFIG_COLOR_BORDER);
+        } else if key_id == CONFIG_BORDER_WIDTH.id() {
+            self.state.border_thickness = self.config.get(CONFIG_BORDER_WIDTH);
+        }
     }
 
     /// Draws the contents of the widget in this order:
@@ -224,6 +256,8 @@ impl Widget for BoxWidget {
     /// - Base widget first
     /// - Box graphic for the specified width
     fn draw(&mut self, c: Context, g: &mut G2d) {
+        self.sync_base_widget();
+
         // Paint the base widget first.  Forcing a draw() call here will ignore invalidation.
         // Invalidation is controlled by the top level widget (this box).
         self.base_widget.draw(c, g);
@@ -235,3 +269,38 @@ impl Widget for BoxWidget {
         self.clear_invalidate();
     }
 }
+
+/// Implementation of the typed state of the `BoxWidget`.  The state is updated by the border
+/// setters, and by `on_config_changed` when a theme, a binding, an animation or a layout changes
+/// the border.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::theme::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let mut theme = Theme::new();
+///     let mut box_widget = BoxWidget::new();
+///
+///     box_widget.set_border_color([1.0, 0.0, 0.0, 1.0]);
+///     assert_eq!(box_widget.state().border_color, [1.0, 0.0, 0.0, 1.0]);
+///
+///     let box_id = widget_store.add_widget(Box::new(box_widget)).unwrap();
+///
+///     theme.set(StyleSelector::widget_type("box"), CONFIG_BORDER_WIDTH, 4);
+///     widget_store.set_theme(theme);
+///
+///     assert_eq!(widget_store.get_state::<BoxWidget>(box_id).unwrap().border_thickness, 4);
+/// # }
+/// ```
+impl StatefulWidget for BoxWidget {
+    type State = BoxState;
+
+    fn state(&self) -> BoxState {
+        self.state.clone()
+    }
+}
@@ -0,0 +1,310 @@
+// Widget Builder
+// Fluent builders and a declarative macro for constructing widget trees.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::*;
+use std::collections::HashMap;
+
+use crate::core::widget_store::*;
+use crate::widget::box_widget::*;
+use crate::widget::config::*;
+use crate::widget::text_widget::*;
+use crate::widget::timer_widget::*;
+use crate::widget::widget::*;
+
+/// This is a `WidgetNode`, a `Widget` along with the children that are added to it when the
+/// node is added to a `WidgetStore`.  Nodes are usually created from a `WidgetBuilder`, or by
+/// the `widgets!` macro, and are added with `WidgetStore::add_widgets`.
+pub struct WidgetNode {
+    widget: Box<dyn Widget>,
+    children: Vec<WidgetNode>,
+}
+
+/// Implementation of the `WidgetNode`.
+impl WidgetNode {
+    /// Creates a new node for the specified `Widget`, without any children.
+    pub fn new(widget: Box<dyn Widget>) -> Self {
+        Self {
+            widget,
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds the specified nodes as children of this node, after any children it already has.
+    pub fn with_children(mut self, children: Vec<WidgetNode>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// Adds the names of the widgets in this node and its descendants to `names`, returning a
+    /// `WidgetStoreError::DuplicateName` error if a name is used more than once.
+    pub(crate) fn collect_names(
+        &mut self,
+        names: &mut Vec<String>,
+    ) -> Result<(), WidgetStoreError> {
+        if let Some(name) = self.widget.get_name() {
+            if names.contains(&name) {
+                return Err(WidgetStoreError::DuplicateName(name));
+            }
+
+            names.push(name);
+        }
+
+        for child in self.children.iter_mut() {
+            child.collect_names(names)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the widget in this node to the `WidgetStore` as a child of `parent_id`, followed by
+    /// its children, recording the IDs of named widgets in `ids`.
+    pub(crate) fn add_to(
+        self,
+        widget_store: &mut WidgetStore,
+        parent_id: i32,
+        ids: &mut HashMap<String, i32>,
+    ) -> Result<(), WidgetStoreError> {
+        let WidgetNode { mut widget, children } = self;
+        let name = widget.get_name();
+        let widget_id = widget_store.add_widget_to_parent(widget, parent_id)?;
+
+        if let Some(name) = name {
+            ids.insert(name, widget_id);
+        }
+
+        for child in children {
+            child.add_to(widget_store, widget_id, ids)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Widget + 'static> From<W> for WidgetNode {
+    fn from(widget: W) -> Self {
+        WidgetNode::new(Box::new(widget))
+    }
+}
+
+impl<W: Widget + 'static> From<WidgetBuilder<W>> for WidgetNode {
+    fn from(builder: WidgetBuilder<W>) -> Self {
+        builder.build()
+    }
+}
+
+/// This is a `WidgetBuilder`, which configures a `Widget` and its children using chained method
+/// calls, instead of a series of `set_*` and `add_widget_to_parent` calls.  Builders are created
+/// with functions such as `BoxWidget::builder()`, or with `WidgetBuilder::new` for any other
+/// widget.  Settings that do not have a builder method can be applied with `config`, or with
+/// a closure passed to `with`.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::builder::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let ids = BoxWidget::builder()
+///         .name("panel")
+///         .origin(500, 80)
+///         .size(200, 200)
+///         .color([0.5, 0.5, 1.0, 1.0])
+///         .border([0.0, 0.0, 1.0, 1.0], 2)
+///         .child(BoxWidget::builder().name("top").origin(550, 105).size(100, 50))
+///         .child(BoxWidget::builder().name("bottom").origin(550, 205).size(100, 50))
+///         .build_into(&mut widget_store, 0)
+///         .unwrap();
+///
+///     assert_eq!(widget_store.get_children_of(ids["panel"]), vec![ids["top"], ids["bottom"]]);
+///     assert_eq!(widget_store.get_state::<BoxWidget>(ids["panel"]).unwrap().border_thickness, 2);
+/// # }
+/// ```
+pub struct WidgetBuilder<W: Widget> {
+    widget: W,
+    children: Vec<WidgetNode>,
+}
+
+/// Implementation of the `WidgetBuilder`.
+impl<W: Widget + 'static> WidgetBuilder<W> {
+    /// Creates a new builder that configures the specified `Widget`.
+    pub fn new(widget: W) -> Self {
+        Self {
+            widget,
+            children: Vec::new(),
+        }
+    }
+
+    /// Sets the origin of the widget.
+    pub fn origin(mut self, x: i32, y: i32) -> Self {
+        self.widget.set_origin(x, y);
+        self
+    }
+
+    /// Sets the size of the widget.
+    pub fn size(mut self, w: i32, h: i32) -> Self {
+        self.widget.set_size(w, h);
+        self
+    }
+
+    /// Sets the color of the widget.
+    pub fn color(mut self, color: types::Color) -> Self {
+        self.widget.set_color(color);
+        self
+    }
+
+    /// Sets the name of the widget.  The IDs of named widgets are returned when the tree is
+    /// added to a `WidgetStore`.
+    pub fn name(mut self, name: &str) -> Self {
+        self.widget.set_name(name);
+        self
+    }
+
+    /// Adds a class to the widget.  Can be called more than once to add several classes.
+    pub fn class(mut self, class: &str) -> Self {
+        self.widget.add_class(class);
+        self
+    }
+
+    /// Shows or hides the widget.
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.widget.set_visible(visible);
+        self
+    }
+
+    /// Enables or disables the widget.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.widget.set_enabled(enabled);
+        self
+    }
+
+    /// Sets a configuration value of the widget.
+    pub fn config<T: 'static>(mut self, key: ConfigKey<T>, value: T) -> Self {
+        set_widget_config(&mut self.widget, key, value);
+        self
+    }
+
+    /// Calls the closure with the widget, for settings and callbacks that do not have a builder
+    /// method.
+    pub fn with<F: FnOnce(&mut W)>(mut self, function: F) -> Self {
+        function(&mut self.widget);
+        self
+    }
+
+    /// Adds a child to the widget, which can be another builder, or a widget.
+    pub fn child<C: Into<WidgetNode>>(mut self, child: C) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    /// Adds several children to the widget, such as those created by the `widgets!` macro.
+    pub fn children(mut self, children: Vec<WidgetNode>) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    /// Finishes the builder, returning a `WidgetNode` that can be added to a `WidgetStore`.
+    pub fn build(self) -> WidgetNode {
+        WidgetNode::new(Box::new(self.widget)).with_children(self.children)
+    }
+
+    /// Adds the widget and its children to the `WidgetStore` as a child of `parent_id`, in the
+    /// same way as `WidgetStore::add_widgets`, returning the IDs of the named widgets.
+    pub fn build_into(
+        self,
+        widget_store: &mut WidgetStore,
+        parent_id: i32,
+    ) -> Result<HashMap<String, i32>, WidgetStoreError> {
+        widget_store.add_widgets(vec![self.build()], parent_id)
+    }
+}
+
+/// Builder functions for the `BoxWidget`.
+impl WidgetBuilder<BoxWidget> {
+    /// Sets the color and the thickness of the border.
+    pub fn border(mut self, color: types::Color, thickness: u8) -> Self {
+        self.widget.set_border(color, thickness);
+        self
+    }
+}
+
+/// Builder functions for the `TextWidget`.
+impl WidgetBuilder<TextWidget> {
+    /// Sets the color of the text.
+    pub fn text_color(mut self, color: types::Color) -> Self {
+        self.widget.set_text_color(color);
+        self
+    }
+}
+
+/// Builder functions for the `TimerWidget`.
+impl WidgetBuilder<TimerWidget> {
+    /// Sets the timeout of the timer in milliseconds.
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.widget.set_timeout(timeout);
+        self
+    }
+}
+
+/// Builds a list of `WidgetNode` objects from a tree literal, to be added to a `WidgetStore`
+/// with `WidgetStore::add_widgets`.  Each entry is a `WidgetBuilder` or a `Widget`, optionally
+/// followed by `=>` and a bracketed list of its children, which are written the same way.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::builder::*;
+/// # use pushrod::widget::timer_widget::*;
+/// # use pushrod::widget::widget::*;
+/// # use pushrod::widgets;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let ids = widget_store.add_widgets(widgets![
+///         BaseWidget::builder().name("sidebar").origin(0, 0).size(200, 600) => [
+///             BoxWidget::builder().name("ok").class("button").size(180, 40),
+///             BoxWidget::builder().class("button").size(180, 40).enabled(false),
+///         ],
+///         BoxWidget::builder().name("content").origin(200, 0).size(600, 600),
+///         TimerWidget::new(),
+///     ], 0).unwrap();
+///
+///     assert_eq!(ids.len(), 3);
+///     assert_eq!(widget_store.get_parent_of(ids["ok"]), ids["sidebar"]);
+///     assert_eq!(widget_store.query("#sidebar > .button:enabled").unwrap(), vec![ids["ok"]]);
+///     assert_eq!(widget_store.find_by_type("timer").len(), 1);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! widgets {
+    (@list [$($out:expr,)*]) => {
+        vec![$($out,)*]
+    };
+    (@list [$($out:expr,)*] $widget:expr => [$($children:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::widgets!(@list [$($out,)*
+            $crate::widget::builder::WidgetNode::from($widget)
+                .with_children($crate::widgets![$($children)*]),
+        ] $($($rest)*)?)
+    };
+    (@list [$($out:expr,)*] $widget:expr $(, $($rest:tt)*)?) => {
+        $crate::widgets!(@list [$($out,)*
+            $crate::widget::builder::WidgetNode::from($widget),
+        ] $($($rest)*)?)
+    };
+    ($($tree:tt)*) => {
+        $crate::widgets!(@list [] $($tree)*)
+    };
+}
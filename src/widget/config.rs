@@ -13,75 +13,233 @@
 // limitations under the License.
 
 use piston_window::types::Color;
+use std::any::{Any, TypeId};
 use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
 
-use crate::core::point::Point;
+use crate::core::point::*;
 
-/// A programmatic type identifying the type of key (and most importantly, size) that is used
-/// for storing configuration values for a `Widget` in the config `HashMap`.
-pub type ConfigKey = u8;
+/// A typed key that is used for storing configuration values for a `Widget` in its
+/// `Configurable` store.  The key carries the type of the value that is stored under it, along
+/// with a default value, so a value can only ever be stored and retrieved as the key's type.
+///
+/// Any crate can define its own keys.  Keys are identified by their name and their value type,
+/// so names should be namespaced to avoid sharing a key with another crate by accident:
+///
+/// ```
+/// # use pushrod::widget::config::*;
+/// pub const CONFIG_PADDING: ConfigKey<u32> = ConfigKey::new("mycrate.padding", || 4);
+///
+/// # fn main() {
+///     let mut config = Configurable::new();
+///
+///     assert_eq!(config.get(CONFIG_PADDING), 4);
+///
+///     config.set(CONFIG_PADDING, 8);
+///     assert_eq!(config.get(CONFIG_PADDING), 8);
+/// # }
+/// ```
+pub struct ConfigKey<T> {
+    name: &'static str,
+    default: fn() -> T,
+    inherited: bool,
+    _value: PhantomData<fn() -> T>,
+}
+
+/// Implementation of the `ConfigKey`.
+impl<T: 'static> ConfigKey<T> {
+    /// Creates a new key with the specified name, and a function that returns the default value
+    /// used when no value has been stored for the key.
+    pub const fn new(name: &'static str, default: fn() -> T) -> Self {
+        Self {
+            name,
+            default,
+            inherited: false,
+            _value: PhantomData,
+        }
+    }
+
+    /// Creates a new inherited key.  A widget that has no value stored for an inherited key,
+    /// either explicitly or by its theme, uses the value of its nearest parent widget that does.
+    pub const fn new_inherited(name: &'static str, default: fn() -> T) -> Self {
+        Self {
+            name,
+            default,
+            inherited: true,
+            _value: PhantomData,
+        }
+    }
+
+    /// Retrieves the name of this key.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Retrieves the default value for this key.
+    pub fn default_value(&self) -> T {
+        (self.default)()
+    }
+
+    /// Retrieves the untyped `ConfigKeyId` identifying this key.
+    pub fn id(&self) -> ConfigKeyId {
+        ConfigKeyId {
+            name: self.name,
+            type_id: TypeId::of::<T>(),
+            inherited: self.inherited,
+        }
+    }
+}
+
+impl<T> Clone for ConfigKey<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            default: self.default,
+            inherited: self.inherited,
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> Copy for ConfigKey<T> {}
+
+/// The untyped identity of a `ConfigKey`, made up of its name, the type of its value, and
+/// whether or not it is inherited.  This is used as the key of the underlying storage, and
+/// wherever keys of different types need to be stored together.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ConfigKeyId {
+    name: &'static str,
+    type_id: TypeId,
+    inherited: bool,
+}
+
+/// Implementation of the `ConfigKeyId`.
+impl ConfigKeyId {
+    /// Retrieves the name of the key.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Indicates whether or not the key's value is inherited from parent widgets.
+    pub fn is_inherited(&self) -> bool {
+        self.inherited
+    }
+}
 
 /// Config entry key for invalidated object (invalidated means "requires screen refresh")
-pub const CONFIG_INVALIDATE: u8 = 0;
+pub const CONFIG_INVALIDATE: ConfigKey<bool> = ConfigKey::new("invalidate", || false);
 
 /// Config entry key for retrieving the `Point` of origin.
-pub const CONFIG_ORIGIN: u8 = 1;
+pub const CONFIG_ORIGIN: ConfigKey<Point> = ConfigKey::new("origin", make_origin_point);
 
 /// Config entry key for retrieving the `Size` of the widget.
-pub const CONFIG_SIZE: u8 = 2;
+pub const CONFIG_SIZE: ConfigKey<Size> = ConfigKey::new("size", make_unsized);
 
 /// Config entry key for autoclipping the widget's drawing area.
-pub const CONFIG_AUTOCLIP: u8 = 3;
+pub const CONFIG_AUTOCLIP: ConfigKey<bool> = ConfigKey::new("autoclip", || false);
+
+/// Config entry key for the name of the widget, which must be unique within a `WidgetStore`.
+/// Defaults to an empty string, meaning that the widget has no name.
+pub const CONFIG_NAME: ConfigKey<String> = ConfigKey::new("name", String::new);
+
+/// Config entry key for the list of classes assigned to the widget, used to select widgets for
+/// theming and lookups.  Defaults to an empty list.
+pub const CONFIG_CLASSES: ConfigKey<Vec<String>> = ConfigKey::new("classes", Vec::new);
 
-/// Config entry key for retrieving the widget's color.
-pub const CONFIG_COLOR: u8 = 4;
+/// Config entry key for the visibility of the widget.  Defaults to `true`.
+pub const CONFIG_VISIBLE: ConfigKey<bool> = ConfigKey::new("visible", || true);
 
-/// Config entry key for retrieving the widget's border color.
-pub const CONFIG_COLOR_BORDER: u8 = 5;
+/// Config entry key for whether or not the widget is enabled.  Defaults to `true`.
+pub const CONFIG_ENABLED: ConfigKey<bool> = ConfigKey::new("enabled", || true);
 
-/// Config entry key for retrieving the widget's border width.
-pub const CONFIG_BORDER_WIDTH: u8 = 6;
+/// Config entry key for retrieving the widget's color: `[f32; 4]` where the values are
+/// `[red, green, blue, transparency]`, values between 0 and 1.0.  Defaults to white.
+pub const CONFIG_COLOR: ConfigKey<Color> = ConfigKey::new("color", || [1.0; 4]);
 
-/// Config entry key for retrieving the widget's text color.
-pub const CONFIG_TEXT_COLOR: u8 = 7;
+/// Config entry key for retrieving the widget's border color.  Defaults to black.
+pub const CONFIG_COLOR_BORDER: ConfigKey<Color> =
+    ConfigKey::new("border-color", || [0.0, 0.0, 0.0, 1.0]);
 
-/// Enumeration data type containing storage areas for each configuration object.
-pub enum WidgetConfig {
-    /// Indicates that a widget's paint contents have become invalidated, and need to be redrawn.
-    Invalidate {},
+/// Config entry key for retrieving the thickness of the border drawn inside widgets that draw
+/// a border.  Defaults to 1.
+pub const CONFIG_BORDER_WIDTH: ConfigKey<u8> = ConfigKey::new("border-width", || 1);
 
-    /// `Point` of origin of this Widget.
-    Origin { point: Point },
+/// Config entry key for retrieving the widget's text color.  Inherited from parent widgets;
+/// defaults to blue.
+pub const CONFIG_TEXT_COLOR: ConfigKey<Color> =
+    ConfigKey::new_inherited("text-color", || [0.0, 0.0, 1.0, 1.0]);
 
-    /// `Size` of this widget.
-    Size { size: crate::core::point::Size },
+/// Config entry key for retrieving the name of the font used to draw text: the filename of the
+/// font in the `assets` directory.  Inherited from parent widgets.
+pub const CONFIG_FONT_NAME: ConfigKey<String> =
+    ConfigKey::new_inherited("font-name", || "OpenSans-Regular.ttf".to_string());
 
-    /// Indicates whether or not to automatically clip the drawing area to the bounds of the
-    /// widget.
-    Autoclip { clip: bool },
+/// Config entry key for retrieving the size of the font used to draw text.  Inherited from
+/// parent widgets; defaults to 16.
+pub const CONFIG_FONT_SIZE: ConfigKey<u32> = ConfigKey::new_inherited("font-size", || 16);
 
-    /// The `types::Color` of this widget: `[f64; 4]` where the values are
-    /// `[red, green, blue, transparency]`, values between 0 and 1.0.
-    Color { color: Color },
+/// Config entry key for retrieving the padding between the bounds of the widget and its
+/// contents.  Defaults to 0.
+pub const CONFIG_PADDING: ConfigKey<u32> = ConfigKey::new("padding", || 0);
 
-    /// The `types::Color` of the border of this widget: `[f64; 4]` where the values are
-    /// `[red, green, blue, transparency]`, values between 0 and 1.0.
-    BorderColor { color: Color },
+/// Config entry key for retrieving the text displayed by the widget.  Defaults to an empty
+/// string.
+pub const CONFIG_TEXT: ConfigKey<String> = ConfigKey::new("text", String::new);
 
-    /// Indicates the thickness of the border width to be drawn inside widgets that draw a
-    /// border.  (See `BorderColor`.)
-    BorderWidth { thickness: u8 },
+/// Identifier assigned to an observer added to a `Configurable`.  Use this ID to remove the
+/// observer with `unobserve`.
+pub type ObserverId = u32;
 
-    /// The `types::Color` of the text for thsi widget: `[f64; 4]` where the values are
-    /// `[red, green, blue, transparency]`, values between 0 and 1.0.
-    TextColor { color: Color },
+/// Closure called when a configuration value changes, receiving the old and new values.
+pub type ConfigObserver<T> = Box<dyn Fn(&T, &T)>;
+
+/// Internal structure used to store an observer for a single key.
+struct KeyObserver {
+    id: ObserverId,
+    key_id: ConfigKeyId,
+    default_value: Rc<dyn Any>,
+    callback: Box<dyn Fn(&dyn Any, &dyn Any)>,
 }
 
 /// This structure is used for the configuration store of `Widget` settings.  It contains its
 /// own structure internally, so all that is used inside extended `Widget` objects is a simple
 /// instantiation of a new `Configurable` object as part of your extension.
+///
+/// Observers can be added for a key, and are called whenever the value for that key changes.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::widget::config::*;
+/// # use std::cell::Cell;
+/// # use std::rc::Rc;
+/// # fn main() {
+///     let mut config = Configurable::new();
+///     let changes = Rc::new(Cell::new(0));
+///     let changes_observer = changes.clone();
+///
+///     let observer_id = config.observe(CONFIG_BORDER_WIDTH, Box::new(move |old, new| {
+///         eprintln!("Border width changed from {} to {}", old, new);
+///         changes_observer.set(changes_observer.get() + 1);
+///     }));
+///
+///     config.set(CONFIG_BORDER_WIDTH, 3);
+///     config.set(CONFIG_BORDER_WIDTH, 3);
+///     assert_eq!(changes.get(), 1);
+///
+///     config.unobserve(observer_id);
+///     config.set(CONFIG_BORDER_WIDTH, 4);
+///     assert_eq!(changes.get(), 1);
+/// # }
+/// ```
 pub struct Configurable {
-    config: HashMap<ConfigKey, WidgetConfig>,
+    config: HashMap<ConfigKeyId, Rc<dyn Any>>,
+    theme: HashMap<ConfigKeyId, Rc<dyn Any>>,
+    inherited: HashMap<ConfigKeyId, Rc<dyn Any>>,
+    observers: Vec<KeyObserver>,
+    next_observer_id: ObserverId,
+    revision: u64,
+    locked: Vec<ConfigKeyId>,
 }
 
 /// Implementation of the `Configurable` object.  Contains methods to extend the `HashMap` that
@@ -91,29 +249,293 @@ impl Configurable {
     pub fn new() -> Self {
         Self {
             config: HashMap::new(),
+            theme: HashMap::new(),
+            inherited: HashMap::new(),
+            observers: Vec::new(),
+            next_observer_id: 1,
+            revision: 0,
+            locked: Vec::new(),
         }
     }
 
-    /// Sets a configuration key by its `ConfigKey` ID, assigning a new `WidgetConfig` value
-    /// to that key.
-    pub fn set(&mut self, key: ConfigKey, value: WidgetConfig) {
-        self.config.insert(key, value);
+    /// Sets a configuration value for the specified `ConfigKey`.  Observers of the key are
+    /// notified if the value has changed.  Keys that are locked keep their value.
+    pub fn set<T: 'static>(&mut self, key: ConfigKey<T>, value: T) {
+        if self.is_locked(key.id()) {
+            return;
+        }
+
+        let previous = self.config.insert(key.id(), Rc::new(value));
+
+        self.bump_revision(key.id());
+        self.notify(key, previous);
     }
 
-    /// Retrieves an `Option<&WidgetConfig>` for the key specified.  If the key does not exist,
-    /// a `None` is returned.
-    pub fn get(&self, key: ConfigKey) -> Option<&WidgetConfig> {
-        self.config.get(&key)
+    /// Retrieves the value stored for the key specified.  If no value is stored, the theme value
+    /// for the key is returned, then the inherited value for inherited keys, and if there is
+    /// none of these, the key's default value.
+    pub fn get<T: Clone + 'static>(&self, key: ConfigKey<T>) -> T {
+        match self.get_ref(key) {
+            Some(value) => value.clone(),
+            None => key.default_value(),
+        }
     }
 
-    /// Removes the value for the specified key, if one exists.
-    pub fn remove(&mut self, key: ConfigKey) {
-        self.config.remove(&key);
+    /// Retrieves an `Option<&T>` for the value stored for the key specified, or its theme or
+    /// inherited value.  If none of these is stored, a `None` is returned.
+    pub fn get_ref<T: 'static>(&self, key: ConfigKey<T>) -> Option<&T> {
+        match self.config.get(&key.id()) {
+            Some(value) => value.downcast_ref::<T>(),
+            None => self
+                .fallback(&key.id())
+                .and_then(|value| value.downcast_ref::<T>()),
+        }
+    }
+
+    /// Retrieves the theme value, or the inherited value, used for a key that is not stored.
+    fn fallback(&self, key_id: &ConfigKeyId) -> Option<&Rc<dyn Any>> {
+        self.theme
+            .get(key_id)
+            .or_else(|| self.inherited.get(key_id))
+    }
+
+    /// Replaces the theme values of this store.  Theme values are used for keys that do not
+    /// have a value stored explicitly.  This is called by the `WidgetStore` when a `Theme` is
+    /// applied to the widget.  Observers of keys whose value changes as a result are notified.
+    /// Returns the IDs of the keys whose value changed.
+    pub fn set_theme_values(
+        &mut self,
+        values: HashMap<ConfigKeyId, Rc<dyn Any>>,
+    ) -> Vec<ConfigKeyId> {
+        let previous = std::mem::replace(&mut self.theme, values);
+        let changed = self.changed_fallback_keys(&previous, &self.inherited);
+
+        self.revision += 1;
+        self.notify_fallback_changes(&previous, &self.inherited);
+        changed
+    }
+
+    /// Retrieves the values of all inherited keys that this store passes on to child widgets:
+    /// its own inherited values, overridden by its theme values and explicitly stored values.
+    pub fn get_inheritable_values(&self) -> HashMap<ConfigKeyId, Rc<dyn Any>> {
+        let mut values = self.inherited.clone();
+
+        for (key_id, value) in self.theme.iter().chain(self.config.iter()) {
+            if key_id.is_inherited() {
+                values.insert(*key_id, value.clone());
+            }
+        }
+
+        values
+    }
+
+    /// Replaces the values inherited from the parent widget.  Returns the IDs of the keys whose
+    /// value changed, whose observers are notified.  Inherited values that are overridden by a
+    /// stored or theme value do not change the value of their key.  This is called by the
+    /// `WidgetStore`, which resolves inherited values by walking the parents of each widget.
+    pub fn set_inherited_values(
+        &mut self,
+        values: HashMap<ConfigKeyId, Rc<dyn Any>>,
+    ) -> Vec<ConfigKeyId> {
+        let previous = std::mem::replace(&mut self.inherited, values);
+        let changed = self.changed_fallback_keys(&self.theme, &previous);
+
+        if !changed.is_empty() {
+            self.revision += 1;
+            self.notify_fallback_changes(&self.theme, &previous);
+        }
+
+        changed
+    }
+
+    /// Retrieves the IDs of the keys that are not stored explicitly, and whose theme or inherited
+    /// value differs from the one under the previous theme and inherited values.
+    fn changed_fallback_keys(
+        &self,
+        previous_theme: &HashMap<ConfigKeyId, Rc<dyn Any>>,
+        previous_inherited: &HashMap<ConfigKeyId, Rc<dyn Any>>,
+    ) -> Vec<ConfigKeyId> {
+        let mut changed: Vec<ConfigKeyId> = Vec::new();
+        let key_ids = previous_theme
+            .keys()
+            .chain(previous_inherited.keys())
+            .chain(self.theme.keys())
+            .chain(self.inherited.keys());
+
+        for key_id in key_ids {
+            if self.config.contains_key(key_id) || changed.contains(key_id) {
+                continue;
+            }
+
+            let old = previous_theme
+                .get(key_id)
+                .or_else(|| previous_inherited.get(key_id));
+
+            let differs = match (old, self.fallback(key_id)) {
+                (Some(old), Some(new)) => !Rc::ptr_eq(old, new),
+                (old, new) => old.is_some() != new.is_some(),
+            };
+
+            if differs {
+                changed.push(*key_id);
+            }
+        }
+
+        changed
+    }
+
+    /// Removes the value for the specified key, if one exists.  Observers of the key are
+    /// notified if the key's default value differs from the removed value.  Keys that are locked
+    /// keep their value.
+    pub fn remove<T: 'static>(&mut self, key: ConfigKey<T>) {
+        if self.is_locked(key.id()) {
+            return;
+        }
+
+        let previous = self.config.remove(&key.id());
+
+        if previous.is_some() {
+            self.bump_revision(key.id());
+            self.notify(key, previous);
+        }
+    }
+
+    /// Locks or unlocks a key.  The value of a locked key is not changed by `set` or `remove`.
+    /// The `WidgetStore` locks `CONFIG_NAME` while a widget is in the store, so that names stay
+    /// unique: widgets in a store are renamed with `WidgetStore::set_widget_name`.
+    pub(crate) fn set_locked(&mut self, key_id: ConfigKeyId, locked: bool) {
+        self.locked.retain(|x| *x != key_id);
+
+        if locked {
+            self.locked.push(key_id);
+        }
+    }
+
+    /// Indicates whether or not a key is locked with `set_locked`.
+    fn is_locked(&self, key_id: ConfigKeyId) -> bool {
+        self.locked.contains(&key_id)
+    }
+
+    /// Retrieves the revision of this store, which increases whenever a value is set or removed,
+    /// or its theme or inherited values change.  Widgets can use this to recompute values
+    /// derived from their configuration only when it has changed.  Invalidating a widget with
+    /// `CONFIG_INVALIDATE` does not change the revision, as it happens on every frame.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Increases the revision after the value of a key has been set or removed, unless the key
+    /// is `CONFIG_INVALIDATE`.
+    fn bump_revision(&mut self, key_id: ConfigKeyId) {
+        if key_id != CONFIG_INVALIDATE.id() {
+            self.revision += 1;
+        }
     }
 
     /// Indicates whether or not a `Configurable` store contains a value for the specified key.
-    /// Returns `true` if one is stored, `false` otherwise.
-    pub fn contains_key(&self, key: ConfigKey) -> bool {
-        self.config.contains_key(&key)
+    /// Returns `true` if one is stored, `false` otherwise.  Theme and inherited values are not
+    /// included.
+    pub fn contains_key<T: 'static>(&self, key: ConfigKey<T>) -> bool {
+        self.config.contains_key(&key.id())
+    }
+
+    /// Retrieves the IDs of the keys that have a value stored in this `Configurable`, in no
+    /// particular order.  Theme and inherited values are not included.
+    pub fn get_keys(&self) -> Vec<ConfigKeyId> {
+        self.config.keys().cloned().collect()
+    }
+
+    /// Adds an observer for the specified key.  The observer is called with the old and new
+    /// values whenever the value for the key changes, either by `set` or by `remove`, or by a
+    /// change of theme or inherited value while no value is stored explicitly.  Unset
+    /// values are reported as the key's default value.  Returns the `ObserverId` of the observer.
+    pub fn observe<T: PartialEq + 'static>(
+        &mut self,
+        key: ConfigKey<T>,
+        callback: ConfigObserver<T>,
+    ) -> ObserverId {
+        let id = self.next_observer_id;
+
+        self.next_observer_id += 1;
+        self.observers.push(KeyObserver {
+            id,
+            key_id: key.id(),
+            default_value: Rc::new(key.default_value()),
+            callback: Box::new(move |old, new| {
+                if let (Some(old), Some(new)) = (old.downcast_ref::<T>(), new.downcast_ref::<T>())
+                {
+                    if old != new {
+                        callback(old, new);
+                    }
+                }
+            }),
+        });
+
+        id
+    }
+
+    /// Removes an observer by its `ObserverId`.
+    pub fn unobserve(&mut self, id: ObserverId) {
+        self.observers.retain(|x| x.id != id);
+    }
+
+    /// Calls the observers of a key with the previous value and the current value for the key.
+    /// Values that are not stored are reported as their theme or inherited value, or the key's
+    /// default value.
+    fn notify<T: 'static>(&self, key: ConfigKey<T>, previous: Option<Rc<dyn Any>>) {
+        let key_id = key.id();
+
+        if !self.observers.iter().any(|x| x.key_id == key_id) {
+            return;
+        }
+
+        let default_value: Box<dyn Any> = Box::new(key.default_value());
+        let unset: &dyn Any = match self.fallback(&key_id) {
+            Some(value) => value.as_ref(),
+            None => default_value.as_ref(),
+        };
+        let old: &dyn Any = match previous {
+            Some(ref previous) => previous.as_ref(),
+            None => unset,
+        };
+        let new: &dyn Any = match self.config.get(&key_id) {
+            Some(value) => value.as_ref(),
+            None => unset,
+        };
+
+        for observer in self.observers.iter().filter(|x| x.key_id == key_id) {
+            (observer.callback)(old, new);
+        }
+    }
+
+    /// Calls the observers of keys that are not stored explicitly with the value they had under
+    /// the previous theme and inherited values, and the value they have now.  Observers are only
+    /// called if the value differs.
+    fn notify_fallback_changes(
+        &self,
+        previous_theme: &HashMap<ConfigKeyId, Rc<dyn Any>>,
+        previous_inherited: &HashMap<ConfigKeyId, Rc<dyn Any>>,
+    ) {
+        for observer in self.observers.iter() {
+            let key_id = &observer.key_id;
+
+            if self.config.contains_key(key_id) {
+                continue;
+            }
+
+            let old: &dyn Any = match previous_theme
+                .get(key_id)
+                .or_else(|| previous_inherited.get(key_id))
+            {
+                Some(value) => value.as_ref(),
+                None => observer.default_value.as_ref(),
+            };
+            let new: &dyn Any = match self.fallback(key_id) {
+                Some(value) => value.as_ref(),
+                None => observer.default_value.as_ref(),
+            };
+
+            (observer.callback)(old, new);
+        }
     }
 }
@@ -0,0 +1,1080 @@
+// Layout
+// Loads trees of widgets from layout files.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::types::Color;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::core::widget_store::*;
+use crate::widget::builder::*;
+use crate::widget::config::*;
+use crate::widget::registry::*;
+use crate::widget::stylesheet::parse_color;
+use crate::widget::widget::*;
+
+/// An error that occurred while loading a layout.
+#[derive(Debug)]
+pub enum LayoutError {
+    /// The layout file could not be read.
+    Io(io::Error),
+
+    /// The layout is not valid JSON.  The line and column (both starting at 1) point to the
+    /// location of the error.
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    /// The layout is valid JSON, but does not describe a valid tree of widgets.  The path points
+    /// to the offending value, such as `widgets[0].children[1].size`.
+    Invalid { path: String, message: String },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayoutError::Io(error) => write!(f, "Unable to read layout: {}", error),
+            LayoutError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}: {}", line, column, message),
+            LayoutError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+impl From<io::Error> for LayoutError {
+    fn from(error: io::Error) -> Self {
+        LayoutError::Io(error)
+    }
+}
+
+/// A value in a layout file.  The properties of a widget are stored as an `Object`, and are
+/// passed to the constructors in a `WidgetRegistry`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum LayoutValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<LayoutValue>),
+
+    /// The members of an object, in the order in which they appear in the file.
+    Object(Vec<(String, LayoutValue)>),
+}
+
+/// Implementation of the `LayoutValue`.
+impl LayoutValue {
+    /// Retrieves a member of an object.  Returns `None` if the value is not an object, or does
+    /// not contain the member.
+    pub fn get(&self, key: &str) -> Option<&LayoutValue> {
+        match self {
+            LayoutValue::Object(members) => members.iter().find(|x| x.0 == key).map(|x| &x.1),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the value of a `Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            LayoutValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the value of a `Number`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            LayoutValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the value of a `String`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            LayoutValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Retrieves the elements of an `Array`.
+    pub fn as_array(&self) -> Option<&Vec<LayoutValue>> {
+        match self {
+            LayoutValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LayoutValue {
+    /// Writes the value as JSON.  Objects, and arrays containing objects or arrays, are written
+    /// over several lines, indented by four spaces for each level.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut output = String::new();
+
+        write_value(self, 0, &mut output);
+        f.write_str(&output)
+    }
+}
+
+/// Parses a layout, creating its widgets with the specified `WidgetRegistry`, and adding them
+/// to the `WidgetStore` as children of the base widget.  Returns the IDs of the named widgets
+/// in the layout, by name.  The widgets are only added if the whole layout is valid.
+///
+/// A layout is a JSON object with a `widgets` array.  Each widget is an object with a `type`,
+/// which is the name of a type in the registry, an optional array of `children`, and the
+/// properties accepted by its type, as described by `WidgetRegistry::get_properties`.  The
+/// following properties are applied to the widget's configuration, for the types that accept
+/// them: `border-color` and `border-width` for `box`, `text` and `padding` for `text`, and the
+/// others for every type:
+///
+/// - `name`: a string that is unique within the `WidgetStore`
+/// - `classes`: an array of strings
+/// - `origin`, `size`: an array of two whole numbers
+/// - `color`, `border-color`, `text-color`: an array of three or four numbers from 0 to 1.0,
+///   or a string in the same format as a style sheet color, such as `#rrggbb`
+/// - `border-width`, `font-size`, `padding`: a whole number
+/// - `font-name`: the filename of a font in the `assets` directory
+/// - `text`: a string
+/// - `visible`, `enabled`, `autoclip`: `true` or `false`
+///
+/// Other properties are passed to the widget's constructor, such as the `timeout` of a
+/// `timer`.  Properties that are not accepted by the widget's type are reported as errors.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::layout::*;
+/// # use pushrod::widget::registry::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut widget_store = WidgetStore::new();
+///     let registry = WidgetRegistry::new();
+///     let ids = parse_layout(&mut widget_store, &registry, r##"{
+///         "widgets": [
+///             {
+///                 "type": "box",
+///                 "name": "panel",
+///                 "origin": [500, 80],
+///                 "size": [200, 200],
+///                 "color": "#8080ff",
+///                 "border-color": [0.0, 0.0, 1.0, 1.0],
+///                 "border-width": 2,
+///                 "children": [
+///                     { "type": "box", "name": "top", "origin": [550, 105], "size": [100, 50] }
+///                 ]
+///             },
+///             { "type": "timer", "timeout": 1000 }
+///         ]
+///     }"##).unwrap();
+///
+///     assert_eq!(widget_store.get_parent_of(ids["top"]), ids["panel"]);
+///     assert_eq!(widget_store.get_state::<BoxWidget>(ids["panel"]).unwrap().border_thickness, 2);
+///     assert_eq!(widget_store.find_by_type("timer").len(), 1);
+///
+///     let error = parse_layout(&mut widget_store, &registry,
+///         r#"{ "widgets": [ { "type": "box", "children": [ { "type": "box", "size": [1] } ] } ] }"#)
+///         .unwrap_err();
+///     assert_eq!(error.to_string(),
+///         "widgets[0].children[0].size: Expected an array of 2 whole numbers");
+///
+///     let error = parse_layout(&mut widget_store, &registry,
+///         r#"{ "widgets": [ { "type": "box", "font-name": "missing.ttf" } ] }"#).unwrap_err();
+///     assert_eq!(error.to_string(),
+///         "widgets[0].font-name: Font 'missing.ttf' not found in the assets folder");
+/// # }
+/// ```
+pub fn parse_layout(
+    widget_store: &mut WidgetStore,
+    registry: &WidgetRegistry,
+    source: &str,
+) -> Result<HashMap<String, i32>, LayoutError> {
+    ParsedLayout::parse(registry, source)?.add_to(widget_store)
+}
+
+/// Reads and parses a layout file, adding its widgets to the `WidgetStore`.  See
+/// `parse_layout` for the format of the file.
+pub fn load_layout<P: AsRef<Path>>(
+    widget_store: &mut WidgetStore,
+    registry: &WidgetRegistry,
+    path: P,
+) -> Result<HashMap<String, i32>, LayoutError> {
+    let source = fs::read_to_string(path)?;
+
+    parse_layout(widget_store, registry, &source)
+}
+
+/// A layout whose widgets have been created, but not yet added to a `WidgetStore`.
+pub(crate) struct ParsedLayout {
+    nodes: Vec<WidgetNode>,
+    name_paths: HashMap<String, String>,
+}
+
+impl ParsedLayout {
+    /// Parses a layout, creating its widgets with the specified `WidgetRegistry`.
+    pub(crate) fn parse(registry: &WidgetRegistry, source: &str) -> Result<Self, LayoutError> {
+        let layout = parse_value(source)?;
+        let widgets = match layout.get("widgets").and_then(LayoutValue::as_array) {
+            Some(widgets) => widgets,
+            None => return invalid("widgets", "Expected an array of widgets".to_string()),
+        };
+        let mut name_paths = HashMap::new();
+        let mut nodes = Vec::new();
+
+        for (i, widget) in widgets.iter().enumerate() {
+            nodes.push(build_node(
+                registry,
+                widget,
+                &format!("widgets[{}]", i),
+                &mut name_paths,
+            )?);
+        }
+
+        Ok(Self { nodes, name_paths })
+    }
+
+    /// Checks that the names of the widgets in the layout are not used by widgets in the
+    /// `WidgetStore`, other than the widgets specified by `replaced_ids`, which are about to be
+    /// removed.
+    pub(crate) fn check_names(
+        &self,
+        widget_store: &mut WidgetStore,
+        replaced_ids: &[i32],
+    ) -> Result<(), LayoutError> {
+        for (name, path) in &self.name_paths {
+            match widget_store.find_by_name(name) {
+                Some(widget_id) if !replaced_ids.contains(&widget_id) => {
+                    return invalid(
+                        &format!("{}.name", path),
+                        WidgetStoreError::DuplicateName(name.clone()).to_string(),
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the widgets to the `WidgetStore` as children of the base widget, returning the IDs
+    /// of the named widgets.
+    pub(crate) fn add_to(
+        self,
+        widget_store: &mut WidgetStore,
+    ) -> Result<HashMap<String, i32>, LayoutError> {
+        let name_paths = self.name_paths;
+
+        widget_store
+            .add_widgets(self.nodes, 0)
+            .map_err(|error| LayoutError::Invalid {
+                path: match &error {
+                    WidgetStoreError::DuplicateName(name) => format!("{}.name", name_paths[name]),
+                    _ => "widgets".to_string(),
+                },
+                message: error.to_string(),
+            })
+    }
+}
+
+/// Converts the widgets in the `WidgetStore` to a layout value, recording the configuration
+/// values that are not written to the layout in `unsaved`, by widget ID.  See
+/// `WidgetStore::to_layout_string`.
+pub(crate) fn save_layout(
+    widget_store: &WidgetStore,
+    unsaved: &mut Vec<(i32, ConfigKeyId)>,
+) -> LayoutValue {
+    LayoutValue::Object(vec![(
+        "widgets".to_string(),
+        LayoutValue::Array(save_children(widget_store, 0, unsaved)),
+    )])
+}
+
+/// Parses a JSON value.
+pub(crate) fn parse_value(source: &str) -> Result<LayoutValue, LayoutError> {
+    let mut parser = Parser::new(source);
+    let value = parser.value()?;
+
+    parser.skip_whitespace();
+
+    match parser.peek() {
+        None => Ok(value),
+        c => parser.error(format!("Unexpected {}", Parser::describe(c))),
+    }
+}
+
+fn invalid<T>(path: &str, message: String) -> Result<T, LayoutError> {
+    Err(LayoutError::Invalid {
+        path: path.to_string(),
+        message,
+    })
+}
+
+/// Creates the widget described at `path`, along with its children, recording the path of each
+/// named widget in `name_paths`.
+fn build_node(
+    registry: &WidgetRegistry,
+    value: &LayoutValue,
+    path: &str,
+    name_paths: &mut HashMap<String, String>,
+) -> Result<WidgetNode, LayoutError> {
+    let members = match value {
+        LayoutValue::Object(members) => members,
+        _ => return invalid(path, "Expected a widget object".to_string()),
+    };
+    let type_path = format!("{}.type", path);
+    let widget_type = match value.get("type") {
+        Some(LayoutValue::String(widget_type)) => widget_type,
+        Some(_) => return invalid(&type_path, "Expected a widget type".to_string()),
+        None => return invalid(path, "Missing widget type".to_string()),
+    };
+
+    if !registry.is_registered(widget_type) {
+        return invalid(&type_path, format!("Unknown widget type '{}'", widget_type));
+    }
+
+    for (key, value) in members {
+        if key != "type" && key != "children" {
+            registry
+                .validate_property(widget_type, key, value)
+                .or_else(|message| invalid(&format!("{}.{}", path, key), message))?;
+        }
+    }
+
+    let mut widget = registry.create(widget_type, value).unwrap();
+    let mut children = Vec::new();
+
+    for (key, value) in members {
+        let property_path = format!("{}.{}", path, key);
+
+        match key.as_str() {
+            "type" => {}
+            "children" => {
+                let values = match value.as_array() {
+                    Some(values) => values,
+                    None => {
+                        return invalid(&property_path, "Expected an array of widgets".to_string())
+                    }
+                };
+
+                for (i, child) in values.iter().enumerate() {
+                    children.push(build_node(
+                        registry,
+                        child,
+                        &format!("{}[{}]", property_path, i),
+                        name_paths,
+                    )?);
+                }
+            }
+            _ => apply_property(registry, widget_type, widget.as_mut(), key, value)
+                .or_else(|message| invalid(&property_path, message))?,
+        }
+    }
+
+    if let Some(name) = widget.get_name() {
+        if name_paths.contains_key(&name) {
+            return invalid(
+                &format!("{}.name", path),
+                WidgetStoreError::DuplicateName(name).to_string(),
+            );
+        }
+
+        name_paths.insert(name, path.to_string());
+    }
+
+    Ok(WidgetNode::new(widget).with_children(children))
+}
+
+/// A layout property that is stored in a widget's configuration, along with the functions that
+/// apply it to a widget when a layout is loaded, and read it back when the layout is saved.
+/// Whether a widget type accepts the property is described by the `WidgetRegistry`.
+struct ConfigProperty {
+    name: &'static str,
+    apply: fn(&mut dyn Widget, &LayoutValue) -> Result<(), String>,
+    save: fn(&Configurable) -> Option<LayoutValue>,
+}
+
+/// The properties listed in `parse_layout`, which are shared by loading and saving layouts.
+fn config_properties() -> Vec<ConfigProperty> {
+    vec![
+        ConfigProperty {
+            name: "name",
+            apply: |widget, value| {
+                widget.set_name(&string(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_NAME, LayoutValue::String),
+        },
+        ConfigProperty {
+            name: "classes",
+            apply: |widget, value| {
+                let classes = value
+                    .as_array()
+                    .filter(|x| x.iter().all(|x| x.as_str().is_some()))
+                    .ok_or_else(|| "Expected an array of strings".to_string())?;
+
+                for class in classes.iter().filter_map(LayoutValue::as_str) {
+                    widget.add_class(class);
+                }
+
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_CLASSES, |classes| {
+                    LayoutValue::Array(classes.into_iter().map(LayoutValue::String).collect())
+                })
+            },
+        },
+        ConfigProperty {
+            name: "origin",
+            apply: |widget, value| {
+                let (x, y) = pair(value)?;
+
+                widget.set_origin(x, y);
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_ORIGIN, |origin| {
+                    number_array(&[origin.x as f64, origin.y as f64])
+                })
+            },
+        },
+        ConfigProperty {
+            name: "size",
+            apply: |widget, value| {
+                let (w, h) = pair(value)?;
+
+                widget.set_size(w, h);
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_SIZE, |size| {
+                    number_array(&[size.w as f64, size.h as f64])
+                })
+            },
+        },
+        ConfigProperty {
+            name: "color",
+            apply: |widget, value| {
+                widget.set_color(color(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_COLOR, color_value),
+        },
+        ConfigProperty {
+            name: "border-color",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_COLOR_BORDER, color(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_COLOR_BORDER, color_value),
+        },
+        ConfigProperty {
+            name: "text-color",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_TEXT_COLOR, color(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_TEXT_COLOR, color_value),
+        },
+        ConfigProperty {
+            name: "border-width",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_BORDER_WIDTH, integer(value)?);
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_BORDER_WIDTH, |width| {
+                    LayoutValue::Number(f64::from(width))
+                })
+            },
+        },
+        ConfigProperty {
+            name: "font-size",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_FONT_SIZE, integer(value)?);
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_FONT_SIZE, |size| {
+                    LayoutValue::Number(f64::from(size))
+                })
+            },
+        },
+        ConfigProperty {
+            name: "padding",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_PADDING, integer(value)?);
+                Ok(())
+            },
+            save: |config| {
+                saved(config, CONFIG_PADDING, |padding| {
+                    LayoutValue::Number(f64::from(padding))
+                })
+            },
+        },
+        ConfigProperty {
+            name: "font-name",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_FONT_NAME, string(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_FONT_NAME, LayoutValue::String),
+        },
+        ConfigProperty {
+            name: "text",
+            apply: |widget, value| {
+                set_widget_config(widget, CONFIG_TEXT, string(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_TEXT, LayoutValue::String),
+        },
+        ConfigProperty {
+            name: "visible",
+            apply: |widget, value| {
+                widget.set_visible(boolean(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_VISIBLE, LayoutValue::Bool),
+        },
+        ConfigProperty {
+            name: "enabled",
+            apply: |widget, value| {
+                widget.set_enabled(boolean(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_ENABLED, LayoutValue::Bool),
+        },
+        ConfigProperty {
+            name: "autoclip",
+            apply: |widget, value| {
+                widget.set_autoclip(boolean(value)?);
+                Ok(())
+            },
+            save: |config| saved(config, CONFIG_AUTOCLIP, LayoutValue::Bool),
+        },
+    ]
+}
+
+/// Converts the value stored for a key to a layout value, if one was set on the widget.
+fn saved<T: Clone + 'static>(
+    config: &Configurable,
+    key: ConfigKey<T>,
+    to_value: impl Fn(T) -> LayoutValue,
+) -> Option<LayoutValue> {
+    Some(key)
+        .filter(|x| config.contains_key(*x))
+        .map(|x| to_value(config.get(x)))
+}
+
+/// Applies a property from a layout file to a widget, once it has been validated by the
+/// `WidgetRegistry`.  Properties that are not listed in `parse_layout` are read by the widget's
+/// constructor instead; they are checked again here, so that any property the widget's type
+/// does not accept is reported as an error.
+fn apply_property(
+    registry: &WidgetRegistry,
+    widget_type: &str,
+    widget: &mut dyn Widget,
+    key: &str,
+    value: &LayoutValue,
+) -> Result<(), String> {
+    match config_properties().into_iter().find(|x| x.name == key) {
+        Some(property) => (property.apply)(widget, value),
+        None => registry.validate_property(widget_type, key, value),
+    }
+}
+
+/// Saves the children of the specified widget, skipping widgets built by a composite widget.
+fn save_children(
+    widget_store: &WidgetStore,
+    parent_id: i32,
+    unsaved: &mut Vec<(i32, ConfigKeyId)>,
+) -> Vec<LayoutValue> {
+    let child_ids: Vec<i32> = widget_store
+        .get_children_of(parent_id)
+        .into_iter()
+        .filter(|x| *x != parent_id && !widget_store.is_built_widget(*x))
+        .collect();
+
+    child_ids
+        .into_iter()
+        .map(|x| save_widget(widget_store, x, unsaved))
+        .collect()
+}
+
+/// Saves a widget and its children, writing the configuration values that were set on the
+/// widget for the properties listed in `parse_layout` that its type accepts, as described by
+/// `WidgetRegistry::get_properties` for the built-in types.  Other configuration values are
+/// recorded in `unsaved`, unless the widget's `get_layout_properties` writes a property with the
+/// name of their key.
+fn save_widget(
+    widget_store: &WidgetStore,
+    widget_id: i32,
+    unsaved: &mut Vec<(i32, ConfigKeyId)>,
+) -> LayoutValue {
+    let widget = widget_store.widgets[widget_id as usize].widget.as_ref();
+    let widget_type = widget.get_widget_type();
+    let config = widget.get_config();
+    let config_properties = config_properties();
+    let mut members = vec![(
+        "type".to_string(),
+        LayoutValue::String(widget_type.to_string()),
+    )];
+
+    let mut properties = WidgetRegistry::common_properties();
+
+    properties.extend(WidgetRegistry::builtin_properties(widget_type));
+
+    for property in properties {
+        let value = config_properties
+            .iter()
+            .find(|x| x.name == property.name)
+            .and_then(|x| (x.save)(config));
+
+        if let Some(value) = value {
+            members.push((property.name, value));
+        }
+    }
+
+    members.extend(widget.get_layout_properties());
+
+    let mut unsaved_keys: Vec<ConfigKeyId> = config
+        .get_keys()
+        .into_iter()
+        .filter(|x| *x != CONFIG_INVALIDATE.id() && members.iter().all(|y| y.0 != x.name()))
+        .collect();
+
+    unsaved_keys.sort_by_key(|x| x.name());
+    unsaved.extend(unsaved_keys.into_iter().map(|x| (widget_id, x)));
+
+    let children = save_children(widget_store, widget_id, unsaved);
+
+    if !children.is_empty() {
+        members.push(("children".to_string(), LayoutValue::Array(children)));
+    }
+
+    LayoutValue::Object(members)
+}
+
+fn number_array(values: &[f64]) -> LayoutValue {
+    LayoutValue::Array(values.iter().map(|x| LayoutValue::Number(*x)).collect())
+}
+
+/// Converts a color to an array of four numbers, as it is written to a layout file.
+pub(crate) fn color_value(color: Color) -> LayoutValue {
+    number_array(&color.iter().map(|x| *x as f64).collect::<Vec<f64>>())
+}
+
+/// Writes a value as JSON, with nested values indented to `indent` levels.
+fn write_value(value: &LayoutValue, indent: usize, output: &mut String) {
+    let nested = |value: &LayoutValue| match value {
+        LayoutValue::Array(_) | LayoutValue::Object(_) => true,
+        _ => false,
+    };
+    let newline = |output: &mut String, indent: usize| {
+        output.push('\n');
+        output.push_str(&"    ".repeat(indent));
+    };
+
+    match value {
+        LayoutValue::Null => output.push_str("null"),
+        LayoutValue::Bool(value) => output.push_str(&value.to_string()),
+        LayoutValue::Number(value) if value.is_finite() => output.push_str(&value.to_string()),
+        LayoutValue::Number(_) => output.push_str("null"),
+        LayoutValue::String(value) => write_string(value, output),
+        LayoutValue::Array(values) if !values.iter().any(nested) => {
+            output.push('[');
+
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+
+                write_value(value, indent, output);
+            }
+
+            output.push(']');
+        }
+        LayoutValue::Array(values) => {
+            output.push('[');
+
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+
+                newline(output, indent + 1);
+                write_value(value, indent + 1, output);
+            }
+
+            newline(output, indent);
+            output.push(']');
+        }
+        LayoutValue::Object(members) if members.is_empty() => output.push_str("{}"),
+        LayoutValue::Object(members) => {
+            output.push('{');
+
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+
+                newline(output, indent + 1);
+                write_string(key, output);
+                output.push_str(": ");
+                write_value(value, indent + 1, output);
+            }
+
+            newline(output, indent);
+            output.push('}');
+        }
+    }
+}
+
+/// Writes a string as JSON, escaping quotes, backslashes and control characters.
+fn write_string(value: &str, output: &mut String) {
+    output.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+}
+
+pub(crate) fn string(value: &LayoutValue) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Expected a string".to_string())
+}
+
+pub(crate) fn boolean(value: &LayoutValue) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| "Expected true or false".to_string())
+}
+
+fn integer<T: TryFrom<i64>>(value: &LayoutValue) -> Result<T, String> {
+    value
+        .as_f64()
+        .filter(|x| x.fract() == 0.0)
+        .and_then(|x| T::try_from(x as i64).ok())
+        .ok_or_else(|| "Expected a whole number in range".to_string())
+}
+
+pub(crate) fn pair(value: &LayoutValue) -> Result<(i32, i32), String> {
+    let invalid = || "Expected an array of 2 whole numbers".to_string();
+    let values = value
+        .as_array()
+        .filter(|x| x.len() == 2)
+        .ok_or_else(invalid)?;
+
+    Ok((
+        integer(&values[0]).map_err(|_| invalid())?,
+        integer(&values[1]).map_err(|_| invalid())?,
+    ))
+}
+
+pub(crate) fn color(value: &LayoutValue) -> Result<Color, String> {
+    match value {
+        LayoutValue::String(value) => parse_color(value),
+        LayoutValue::Array(values) if values.len() == 3 || values.len() == 4 => {
+            let mut color = [1.0; 4];
+
+            for (i, value) in values.iter().enumerate() {
+                color[i] = value
+                    .as_f64()
+                    .filter(|x| *x >= 0.0 && *x <= 1.0)
+                    .ok_or_else(|| "Expected color components from 0 to 1.0".to_string())?
+                    as f32;
+            }
+
+            Ok(color)
+        }
+        _ => Err("Expected a color".to_string()),
+    }
+}
+
+/// Internal JSON parser, which keeps track of the current line and column for error reporting.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.pos += 1;
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, LayoutError> {
+        Err(LayoutError::Parse {
+            line: self.line,
+            column: self.column,
+            message,
+        })
+    }
+
+    fn describe(c: Option<char>) -> String {
+        match c {
+            Some(c) => format!("'{}'", c),
+            None => "end of file".to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), LayoutError> {
+        self.skip_whitespace();
+
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
+        } else {
+            self.error(format!(
+                "Expected '{}', found {}",
+                expected,
+                Self::describe(self.peek())
+            ))
+        }
+    }
+
+    fn value(&mut self) -> Result<LayoutValue, LayoutError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(LayoutValue::String(self.string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                let (line, column) = (self.line, self.column);
+
+                while self.peek().map_or(false, char::is_alphanumeric) {
+                    self.next();
+                }
+
+                let word: String = self.chars[start..self.pos].iter().collect();
+
+                match word.as_str() {
+                    "true" => Ok(LayoutValue::Bool(true)),
+                    "false" => Ok(LayoutValue::Bool(false)),
+                    "null" => Ok(LayoutValue::Null),
+                    _ => Err(LayoutError::Parse {
+                        line,
+                        column,
+                        message: format!("Expected a value, found '{}'", word),
+                    }),
+                }
+            }
+            c => self.error(format!("Expected a value, found {}", Self::describe(c))),
+        }
+    }
+
+    fn object(&mut self) -> Result<LayoutValue, LayoutError> {
+        let mut members: Vec<(String, LayoutValue)> = Vec::new();
+
+        self.expect('{')?;
+        self.skip_whitespace();
+
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(LayoutValue::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            if self.peek() != Some('"') {
+                return self.error(format!(
+                    "Expected a property name, found {}",
+                    Self::describe(self.peek())
+                ));
+            }
+
+            let (line, column) = (self.line, self.column);
+            let key = self.string()?;
+
+            if members.iter().any(|x| x.0 == key) {
+                return Err(LayoutError::Parse {
+                    line,
+                    column,
+                    message: format!("Duplicate property '{}'", key),
+                });
+            }
+
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some('}') => {
+                    self.next();
+                    return Ok(LayoutValue::Object(members));
+                }
+                c => {
+                    return self.error(format!("Expected ',' or '}}', found {}", Self::describe(c)))
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<LayoutValue, LayoutError> {
+        let mut values = Vec::new();
+
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(LayoutValue::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some(']') => {
+                    self.next();
+                    return Ok(LayoutValue::Array(values));
+                }
+                c => {
+                    return self.error(format!("Expected ',' or ']', found {}", Self::describe(c)))
+                }
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, LayoutError> {
+        let mut value = String::new();
+
+        self.next();
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.next()).collect();
+
+                            match u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(std::char::from_u32)
+                            {
+                                Some(c) => c,
+                                None => {
+                                    return self
+                                        .error(format!("Invalid unicode escape '\\u{}'", hex))
+                                }
+                            }
+                        }
+                        c => return self.error(format!("Invalid escape {}", Self::describe(c))),
+                    };
+
+                    value.push(c);
+                }
+                Some(c) => value.push(c),
+                None => return self.error("Unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<LayoutValue, LayoutError> {
+        let start = self.pos;
+        let (line, column) = (self.line, self.column);
+
+        while self
+            .peek()
+            .map_or(false, |c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.next();
+        }
+
+        let number: String = self.chars[start..self.pos].iter().collect();
+
+        match number.parse::<f64>() {
+            Ok(value) => Ok(LayoutValue::Number(value)),
+            Err(_) => Err(LayoutError::Parse {
+                line,
+                column,
+                message: format!("Expected a number, found '{}'", number),
+            }),
+        }
+    }
+}
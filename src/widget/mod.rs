@@ -27,3 +27,20 @@ pub mod text_widget;
 
 /// `Configurable` definition, used by `Widget` objects to store configuration settings.
 pub mod config;
+
+/// `Theme` definition, which provides default configuration values for widgets by widget type
+/// and interaction state.
+pub mod theme;
+
+/// Style sheet parser, which loads `Theme` rules from a CSS-like file format.
+pub mod stylesheet;
+
+/// Fluent `WidgetBuilder` and the `widgets!` macro, used to construct trees of widgets and add
+/// them to a `WidgetStore` in a single call.
+pub mod builder;
+
+/// `WidgetRegistry` definition, which creates widgets by their type name.
+pub mod registry;
+
+/// Layout file loader, which creates trees of widgets from a JSON description.
+pub mod layout;
@@ -0,0 +1,408 @@
+// Widget Registry
+// Creates widgets by their type name, and describes their properties.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::*;
+use std::collections::HashMap;
+
+use crate::widget::box_widget::*;
+use crate::widget::config::*;
+use crate::widget::layout::*;
+use crate::widget::text_widget::*;
+use crate::widget::timer_widget::*;
+use crate::widget::widget::*;
+
+/// A function that creates a `Widget`, given the properties of the widget from a layout file.
+pub type WidgetConstructor = Box<dyn Fn(&LayoutValue) -> Box<dyn Widget>>;
+
+/// The largest `TimerWidget` timeout in a layout file: the largest whole number that the `f64`
+/// numbers of a layout file store exactly.
+const MAX_TIMEOUT: i64 = 1 << 53;
+
+/// The type of value that a widget property accepts.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PropertyType {
+    /// `true` or `false`.
+    Bool,
+
+    /// A whole number from `min` to `max`.
+    Integer { min: i64, max: i64 },
+
+    /// A string.
+    String,
+
+    /// The filename of a font in the `assets` directory.
+    FontName,
+
+    /// An array of strings.
+    StringList,
+
+    /// An array of two whole numbers, such as an origin or a size.
+    Pair,
+
+    /// A color: an array of three or four numbers from 0 to 1.0, or a string in the same format
+    /// as a style sheet color.
+    Color,
+}
+
+/// Implementation of the `PropertyType`.
+impl PropertyType {
+    /// Checks that a value from a layout file is of this type, returning a message describing
+    /// the expected value if it is not.
+    pub fn validate(&self, value: &LayoutValue) -> Result<(), String> {
+        match self {
+            PropertyType::Bool => boolean(value).map(|_| ()),
+            PropertyType::Integer { min, max } => value
+                .as_f64()
+                .filter(|x| x.fract() == 0.0 && *x >= *min as f64 && *x <= *max as f64)
+                .map(|_| ())
+                .ok_or_else(|| format!("Expected a whole number from {} to {}", min, max)),
+            PropertyType::String => string(value).map(|_| ()),
+            PropertyType::FontName => TextWidget::find_font(&string(value)?).map(|_| ()),
+            PropertyType::StringList => value
+                .as_array()
+                .filter(|x| x.iter().all(|x| x.as_str().is_some()))
+                .map(|_| ())
+                .ok_or_else(|| "Expected an array of strings".to_string()),
+            PropertyType::Pair => pair(value).map(|_| ()),
+            PropertyType::Color => color(value).map(|_| ()),
+        }
+    }
+}
+
+/// Describes a property of a widget type that can be set in a layout file: its name, the type
+/// of value it accepts, and the value used when it is not set.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PropertyDescriptor {
+    pub name: String,
+    pub property_type: PropertyType,
+    pub default: LayoutValue,
+}
+
+/// Implementation of the `PropertyDescriptor`.
+impl PropertyDescriptor {
+    /// Creates a new `PropertyDescriptor`.
+    pub fn new(name: &str, property_type: PropertyType, default: LayoutValue) -> Self {
+        Self {
+            name: name.to_string(),
+            property_type,
+            default,
+        }
+    }
+}
+
+/// A registered widget type.
+struct WidgetType {
+    constructor: WidgetConstructor,
+    properties: Vec<PropertyDescriptor>,
+}
+
+/// This is a `WidgetRegistry`, which maps widget type names, as returned by
+/// `Widget::get_widget_type`, to functions that create widgets of that type.  It is used to
+/// create widgets from layout files, and describes the properties of each type, so that tools
+/// can list them and validate their values.
+///
+/// Every type accepts the properties that are read by `parse_layout` for all widgets: `name`,
+/// `classes`, `origin`, `size`, `color`, `visible`, `enabled` and `autoclip`, along with the
+/// inherited `text-color`, `font-name` and `font-size`.  Other properties are described when
+/// the type is registered.
+///
+/// A new registry contains the `base`, `box` and `timer` types.  The `text` type requires the
+/// window's factory to load fonts, so it is registered by `WidgetRegistry::with_factory`.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::layout::*;
+/// # use pushrod::widget::registry::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut registry = WidgetRegistry::new();
+///
+///     registry.register("button", |_| {
+///         let mut button = BoxWidget::new();
+///
+///         button.add_class("button");
+///         Box::new(button)
+///     });
+///
+///     let mut button = registry.create("button", &LayoutValue::Null).unwrap();
+///
+///     assert!(button.has_class("button"));
+///     assert!(registry.create("slider", &LayoutValue::Null).is_none());
+///     assert_eq!(registry.get_type_names(), vec!["base", "box", "button", "timer"]);
+///
+///     // Property descriptors list the properties of a type, and validate their values.
+///     let properties = registry.get_properties("box").unwrap();
+///     let border_width = properties.iter().find(|x| x.name == "border-width").unwrap();
+///
+///     assert_eq!(border_width.property_type, PropertyType::Integer { min: 0, max: 255 });
+///     assert_eq!(border_width.default, LayoutValue::Number(1.0));
+///     assert!(registry.validate_property("box", "border-width", &LayoutValue::Number(2.0)).is_ok());
+///     assert_eq!(registry.validate_property("box", "border-width", &LayoutValue::Number(300.0)),
+///         Err("Expected a whole number from 0 to 255".to_string()));
+///     assert_eq!(registry.validate_property("base", "timeout", &LayoutValue::Number(1.0)),
+///         Err("Unknown property 'timeout' for widget type 'base'".to_string()));
+///     assert_eq!(registry.validate_property("base", "font-name",
+///         &LayoutValue::String("missing.ttf".to_string())),
+///         Err("Font 'missing.ttf' not found in the assets folder".to_string()));
+/// # }
+/// ```
+pub struct WidgetRegistry {
+    types: HashMap<String, WidgetType>,
+}
+
+/// Implementation of the `WidgetRegistry`.
+impl WidgetRegistry {
+    /// Creates a new registry containing the `base`, `box` and `timer` widget types.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            types: HashMap::new(),
+        };
+
+        registry.register("base", |_| Box::new(BaseWidget::new()));
+        registry.register_with_properties("box", Self::builtin_properties("box"), |_| {
+            Box::new(BoxWidget::new())
+        });
+        registry.register_with_properties(
+            "timer",
+            Self::builtin_properties("timer"),
+            |properties| {
+                let mut timer = TimerWidget::new();
+
+                if let Some(timeout) = properties.get("timeout").and_then(LayoutValue::as_f64) {
+                    timer.set_timeout(timeout as u64);
+                }
+
+                Box::new(timer)
+            },
+        );
+
+        registry
+    }
+
+    /// Creates a new registry containing the `base`, `box`, `text` and `timer` widget types,
+    /// using the current `PistonWindow`'s factory object (which can be cloned) to create
+    /// `TextWidget` objects.
+    pub fn with_factory(factory: GfxFactory) -> Self {
+        let mut registry = Self::new();
+
+        registry.register_with_properties("text", Self::builtin_properties("text"), move |_| {
+            Box::new(TextWidget::with_inherited_font(
+                factory.clone(),
+                String::new(),
+            ))
+        });
+
+        registry
+    }
+
+    /// Registers a function that creates widgets of the specified type, replacing any type that
+    /// was previously registered with the same name.  The type only accepts the properties that
+    /// are common to all widgets.  See `register_with_properties`.
+    pub fn register<F>(&mut self, widget_type: &str, constructor: F)
+    where
+        F: Fn(&LayoutValue) -> Box<dyn Widget> + 'static,
+    {
+        self.register_with_properties(widget_type, Vec::new(), constructor);
+    }
+
+    /// Registers a function that creates widgets of the specified type, along with the
+    /// properties the type accepts in addition to the properties that are common to all widgets,
+    /// replacing any type that was previously registered with the same name.  The function
+    /// receives the properties of the widget from the layout file, after they have been
+    /// validated, for settings that are not stored in the widget's configuration; the
+    /// configuration values are applied after the widget is created.
+    pub fn register_with_properties<F>(
+        &mut self,
+        widget_type: &str,
+        properties: Vec<PropertyDescriptor>,
+        constructor: F,
+    ) where
+        F: Fn(&LayoutValue) -> Box<dyn Widget> + 'static,
+    {
+        self.types.insert(
+            widget_type.to_string(),
+            WidgetType {
+                constructor: Box::new(constructor),
+                properties,
+            },
+        );
+    }
+
+    /// Indicates whether or not a widget type has been registered.
+    pub fn is_registered(&self, widget_type: &str) -> bool {
+        self.types.contains_key(widget_type)
+    }
+
+    /// Retrieves the names of the registered widget types, in alphabetical order.
+    pub fn get_type_names(&self) -> Vec<String> {
+        let mut type_names: Vec<String> = self.types.keys().cloned().collect();
+
+        type_names.sort();
+        type_names
+    }
+
+    /// Retrieves the properties accepted by the specified widget type: the properties common to
+    /// all widgets, followed by the properties registered for the type.  Returns `None` if the
+    /// type has not been registered.
+    pub fn get_properties(&self, widget_type: &str) -> Option<Vec<PropertyDescriptor>> {
+        self.types.get(widget_type).map(|x| {
+            let mut properties = Self::common_properties();
+
+            properties.extend(x.properties.iter().cloned());
+            properties
+        })
+    }
+
+    /// Checks that the specified widget type accepts a property, and that the value is of the
+    /// property's type, returning a message describing the problem if it does not.
+    pub fn validate_property(
+        &self,
+        widget_type: &str,
+        name: &str,
+        value: &LayoutValue,
+    ) -> Result<(), String> {
+        let properties = self
+            .get_properties(widget_type)
+            .ok_or_else(|| format!("Unknown widget type '{}'", widget_type))?;
+
+        match properties.iter().find(|x| x.name == name) {
+            Some(property) => property.property_type.validate(value),
+            None => Err(format!(
+                "Unknown property '{}' for widget type '{}'",
+                name, widget_type
+            )),
+        }
+    }
+
+    /// Creates a widget of the specified type, given its properties.  Returns `None` if the type
+    /// has not been registered.
+    pub fn create(&self, widget_type: &str, properties: &LayoutValue) -> Option<Box<dyn Widget>> {
+        self.types
+            .get(widget_type)
+            .map(|x| (x.constructor)(properties))
+    }
+
+    /// The properties accepted by every widget type, with the default values of their
+    /// configuration keys.
+    pub(crate) fn common_properties() -> Vec<PropertyDescriptor> {
+        let origin = CONFIG_ORIGIN.default_value();
+        let size = CONFIG_SIZE.default_value();
+        let pair_value = |a: i32, b: i32| {
+            LayoutValue::Array(vec![
+                LayoutValue::Number(a as f64),
+                LayoutValue::Number(b as f64),
+            ])
+        };
+
+        vec![
+            PropertyDescriptor::new(
+                "name",
+                PropertyType::String,
+                LayoutValue::String(CONFIG_NAME.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "classes",
+                PropertyType::StringList,
+                LayoutValue::Array(Vec::new()),
+            ),
+            PropertyDescriptor::new("origin", PropertyType::Pair, pair_value(origin.x, origin.y)),
+            PropertyDescriptor::new("size", PropertyType::Pair, pair_value(size.w, size.h)),
+            PropertyDescriptor::new(
+                "color",
+                PropertyType::Color,
+                color_value(CONFIG_COLOR.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "text-color",
+                PropertyType::Color,
+                color_value(CONFIG_TEXT_COLOR.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "font-name",
+                PropertyType::FontName,
+                LayoutValue::String(CONFIG_FONT_NAME.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "font-size",
+                PropertyType::Integer {
+                    min: 0,
+                    max: i64::from(u32::max_value()),
+                },
+                LayoutValue::Number(CONFIG_FONT_SIZE.default_value() as f64),
+            ),
+            PropertyDescriptor::new(
+                "visible",
+                PropertyType::Bool,
+                LayoutValue::Bool(CONFIG_VISIBLE.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "enabled",
+                PropertyType::Bool,
+                LayoutValue::Bool(CONFIG_ENABLED.default_value()),
+            ),
+            PropertyDescriptor::new(
+                "autoclip",
+                PropertyType::Bool,
+                LayoutValue::Bool(CONFIG_AUTOCLIP.default_value()),
+            ),
+        ]
+    }
+
+    /// The properties registered for the built-in widget types, in addition to the properties
+    /// common to all widgets.  Layouts are saved with the same properties, so that a saved
+    /// layout only contains properties that can be loaded again.
+    pub(crate) fn builtin_properties(widget_type: &str) -> Vec<PropertyDescriptor> {
+        match widget_type {
+            "box" => vec![
+                PropertyDescriptor::new(
+                    "border-color",
+                    PropertyType::Color,
+                    color_value(CONFIG_COLOR_BORDER.default_value()),
+                ),
+                PropertyDescriptor::new(
+                    "border-width",
+                    PropertyType::Integer { min: 0, max: 255 },
+                    LayoutValue::Number(CONFIG_BORDER_WIDTH.default_value() as f64),
+                ),
+            ],
+            "text" => vec![
+                PropertyDescriptor::new(
+                    "text",
+                    PropertyType::String,
+                    LayoutValue::String(CONFIG_TEXT.default_value()),
+                ),
+                PropertyDescriptor::new(
+                    "padding",
+                    PropertyType::Integer {
+                        min: 0,
+                        max: i64::from(u32::max_value()),
+                    },
+                    LayoutValue::Number(CONFIG_PADDING.default_value() as f64),
+                ),
+            ],
+            "timer" => vec![PropertyDescriptor::new(
+                "timeout",
+                PropertyType::Integer {
+                    min: 0,
+                    max: MAX_TIMEOUT,
+                },
+                LayoutValue::Number(0.0),
+            )],
+            _ => Vec::new(),
+        }
+    }
+}
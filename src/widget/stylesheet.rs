@@ -0,0 +1,567 @@
+// Style Sheet
+// Parses a CSS-like style sheet into a Theme
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use piston_window::types::Color;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::widget::config::*;
+use crate::widget::text_widget::*;
+use crate::widget::theme::*;
+
+/// An error that occurred while loading a style sheet.
+#[derive(Debug)]
+pub enum StylesheetError {
+    /// The style sheet file could not be read.
+    Io(io::Error),
+
+    /// The style sheet could not be parsed.  The line and column (both starting at 1) point to
+    /// the location of the error.
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for StylesheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StylesheetError::Io(error) => write!(f, "Unable to read style sheet: {}", error),
+            StylesheetError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}: {}", line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for StylesheetError {}
+
+impl From<io::Error> for StylesheetError {
+    fn from(error: io::Error) -> Self {
+        StylesheetError::Io(error)
+    }
+}
+
+/// Parses a style sheet, adding its rules to the specified `Theme`.  Rules that appear later in
+/// the style sheet take precedence over earlier rules with the same specificity.  If the style
+/// sheet contains an error, none of its rules are added.
+///
+/// The style sheet format is a subset of CSS.  A rule consists of a comma-separated list of
+/// selectors followed by a block of `property: value;` declarations.  A selector is made up of
+/// an optional widget type (or `*` for any type), followed by an optional `#name`, `.class` and
+/// `:state`.  The states are `normal`, `hover`, `pressed`, `disabled` and `focused`.  Comments
+/// are written as `/* ... */`.
+///
+/// The following properties are supported:
+///
+/// - `color`, `border-color`, `text-color`: `#rrggbb`, `#rrggbbaa`, `rgb(r, g, b)` or
+///   `rgba(r, g, b, a)`, where `r`, `g` and `b` are between 0 and 255, and `a` is between 0
+///   and 1.0
+/// - `border-width`, `font-size`, `padding`: a whole number, optionally followed by `px`
+/// - `font-name`: the filename of a font in the `assets` directory, optionally in quotes; fonts
+///   that cannot be found are reported as errors
+///
+/// Example usage:
+/// ```
+/// # use pushrod::widget::stylesheet::*;
+/// # use pushrod::widget::theme::*;
+/// # fn main() {
+///     let mut theme = Theme::new();
+///
+///     parse_stylesheet(&mut theme, "
+///         /* Defaults for all widgets */
+///         * { color: #e6e6e6; }
+///
+///         box, box:pressed {
+///             border-width: 2px;
+///             border-color: rgb(0, 0, 0);
+///         }
+///
+///         box:hover { border-color: rgba(0, 0, 255, 0.5); }
+///     ").unwrap();
+///
+///     let error = parse_stylesheet(&mut theme, "box {\n  border-width: thick;\n}").unwrap_err();
+///     assert_eq!(error.to_string(), "2:17: Expected a whole number, found 'thick'");
+///
+///     // The valid rule before the error is not added either.
+///     let mut partial_theme = Theme::new();
+///
+///     let source = "text { padding: 4; } box { size: 1; }";
+///
+///     assert!(parse_stylesheet(&mut partial_theme, source).is_err());
+///     assert!(partial_theme
+///         .resolve(&StyleTarget {
+///             widget_type: "text".to_string(),
+///             name: None,
+///             classes: vec![],
+///             state: WidgetState::Normal,
+///         })
+///         .is_empty());
+/// # }
+/// ```
+pub fn parse_stylesheet(theme: &mut Theme, source: &str) -> Result<(), StylesheetError> {
+    let mut parsed = Theme::new();
+
+    Parser::new(source).parse(&mut parsed)?;
+    theme.merge(parsed);
+
+    Ok(())
+}
+
+/// Reads and parses a style sheet file, adding its rules to the specified `Theme`.  See
+/// `parse_stylesheet` for the format of the file.
+pub fn load_stylesheet<P: AsRef<Path>>(theme: &mut Theme, path: P) -> Result<(), StylesheetError> {
+    let source = fs::read_to_string(path)?;
+
+    parse_stylesheet(theme, &source)
+}
+
+/// Internal parser, which keeps track of the current line and column for error reporting.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.pos += 1;
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error_at<T>(
+        &self,
+        line: usize,
+        column: usize,
+        message: String,
+    ) -> Result<T, StylesheetError> {
+        Err(StylesheetError::Parse {
+            line,
+            column,
+            message,
+        })
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, StylesheetError> {
+        self.error_at(self.line, self.column, message)
+    }
+
+    fn describe(c: Option<char>) -> String {
+        match c {
+            Some(c) => format!("'{}'", c),
+            None => "end of file".to_string(),
+        }
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<(), StylesheetError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') if self.chars.get(self.pos + 1) == Some(&'*') => {
+                    let (line, column) = (self.line, self.column);
+
+                    self.next();
+                    self.next();
+
+                    loop {
+                        match self.next() {
+                            Some('*') if self.peek() == Some('/') => {
+                                self.next();
+                                break;
+                            }
+                            Some(_) => (),
+                            None => {
+                                return self.error_at(
+                                    line,
+                                    column,
+                                    "Unterminated comment".to_string(),
+                                )
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), StylesheetError> {
+        self.skip_whitespace()?;
+
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
+        } else {
+            self.error(format!(
+                "Expected '{}', found {}",
+                expected,
+                Self::describe(self.peek())
+            ))
+        }
+    }
+
+    fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '-' || c == '_'
+    }
+
+    /// Reads an identifier: a sequence of letters, digits, dashes and underscores.
+    fn ident(&mut self, what: &str) -> Result<String, StylesheetError> {
+        let mut ident = String::new();
+
+        while let Some(c) = self.peek().filter(|c| Self::is_ident_char(*c)) {
+            ident.push(c);
+            self.next();
+        }
+
+        if ident.is_empty() {
+            self.error(format!(
+                "Expected {}, found {}",
+                what,
+                Self::describe(self.peek())
+            ))
+        } else {
+            Ok(ident)
+        }
+    }
+
+    fn parse(&mut self, theme: &mut Theme) -> Result<(), StylesheetError> {
+        loop {
+            self.skip_whitespace()?;
+
+            if self.peek().is_none() {
+                return Ok(());
+            }
+
+            let mut selectors = vec![self.selector()?];
+
+            loop {
+                self.skip_whitespace()?;
+
+                if self.peek() == Some(',') {
+                    self.next();
+                    self.skip_whitespace()?;
+                    selectors.push(self.selector()?);
+                } else {
+                    break;
+                }
+            }
+
+            self.expect('{')?;
+            self.declarations(theme, &selectors)?;
+        }
+    }
+
+    /// Parses a single selector, such as `box#toolbar.large:hover`.
+    fn selector(&mut self) -> Result<StyleSelector, StylesheetError> {
+        let (line, column) = (self.line, self.column);
+        let mut selector = StyleSelector::any();
+        let mut empty = true;
+        let mut has_name = false;
+        let mut has_class = false;
+        let mut has_state = false;
+
+        if self.peek() == Some('*') {
+            self.next();
+            empty = false;
+        } else if self.peek().map_or(false, Self::is_ident_char) {
+            selector = selector.with_type(&self.ident("a widget type")?);
+            empty = false;
+        }
+
+        loop {
+            let (part_line, part_column) = (self.line, self.column);
+
+            match self.peek() {
+                Some('#') if !has_name => {
+                    self.next();
+                    selector = selector.with_name(&self.ident("a widget name")?);
+                    has_name = true;
+                }
+                Some('.') if !has_class => {
+                    self.next();
+                    selector = selector.with_class(&self.ident("a class name")?);
+                    has_class = true;
+                }
+                Some(':') if !has_state => {
+                    self.next();
+
+                    let state = match self.ident("a state")?.as_str() {
+                        "normal" => WidgetState::Normal,
+                        "hover" => WidgetState::Hover,
+                        "pressed" => WidgetState::Pressed,
+                        "disabled" => WidgetState::Disabled,
+                        "focused" => WidgetState::Focused,
+                        other => {
+                            return self.error_at(
+                                part_line,
+                                part_column + 1,
+                                format!("Unknown state '{}'", other),
+                            )
+                        }
+                    };
+
+                    selector = selector.with_state(state);
+                    has_state = true;
+                }
+                Some(c @ '#') | Some(c @ '.') | Some(c @ ':') => {
+                    return self.error(format!("Only one '{}' is allowed in a selector", c));
+                }
+                _ => break,
+            }
+
+            empty = false;
+        }
+
+        if empty {
+            self.error_at(
+                line,
+                column,
+                format!("Expected a selector, found {}", Self::describe(self.peek())),
+            )
+        } else {
+            Ok(selector)
+        }
+    }
+
+    /// Parses the declarations of a rule after its opening brace, up to and including the
+    /// closing brace, setting the values in the `Theme` for each of the selectors.
+    fn declarations(
+        &mut self,
+        theme: &mut Theme,
+        selectors: &[StyleSelector],
+    ) -> Result<(), StylesheetError> {
+        loop {
+            self.skip_whitespace()?;
+
+            match self.peek() {
+                Some('}') => {
+                    self.next();
+                    return Ok(());
+                }
+                None => return self.error("Expected '}', found end of file".to_string()),
+                _ => (),
+            }
+
+            let (line, column) = (self.line, self.column);
+            let property = self.ident("a property name")?;
+
+            self.expect(':')?;
+            self.skip_whitespace()?;
+
+            let (value_line, value_column) = (self.line, self.column);
+            let mut value = String::new();
+
+            while let Some(c) = self.peek().filter(|c| *c != ';' && *c != '}') {
+                value.push(c);
+                self.next();
+            }
+
+            if self.peek() == Some(';') {
+                self.next();
+            }
+
+            let value = value.trim_end();
+            let error = move |message: String| StylesheetError::Parse {
+                line: value_line,
+                column: value_column,
+                message,
+            };
+
+            for selector in selectors.iter() {
+                let selector = selector.clone();
+
+                match property.as_str() {
+                    "color" => {
+                        theme.set(selector, CONFIG_COLOR, parse_color(value).map_err(error)?)
+                    }
+                    "border-color" => theme.set(
+                        selector,
+                        CONFIG_COLOR_BORDER,
+                        parse_color(value).map_err(error)?,
+                    ),
+                    "text-color" => theme.set(
+                        selector,
+                        CONFIG_TEXT_COLOR,
+                        parse_color(value).map_err(error)?,
+                    ),
+                    "border-width" => theme.set(
+                        selector,
+                        CONFIG_BORDER_WIDTH,
+                        parse_number(value).map_err(error)?,
+                    ),
+                    "font-size" => theme.set(
+                        selector,
+                        CONFIG_FONT_SIZE,
+                        parse_number(value).map_err(error)?,
+                    ),
+                    "padding" => theme.set(
+                        selector,
+                        CONFIG_PADDING,
+                        parse_number(value).map_err(error)?,
+                    ),
+                    "font-name" => theme.set(
+                        selector,
+                        CONFIG_FONT_NAME,
+                        parse_font_name(value).map_err(error)?,
+                    ),
+                    _ => {
+                        return self.error_at(
+                            line,
+                            column,
+                            format!("Unknown property '{}'", property),
+                        )
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses a color value in the form `#rrggbb`, `#rrggbbaa`, `rgb(r, g, b)` or `rgba(r, g, b, a)`.
+pub(crate) fn parse_color(value: &str) -> Result<Color, String> {
+    let invalid = || format!("Expected a color, found '{}'", value);
+
+    if value.starts_with('#') {
+        let hex = &value[1..];
+
+        if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let component =
+            |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap() as f32 / 255.0;
+
+        Ok([
+            component(0),
+            component(1),
+            component(2),
+            if hex.len() == 8 { component(3) } else { 1.0 },
+        ])
+    } else {
+        let (arguments, count) = if value.starts_with("rgba(") && value.ends_with(')') {
+            (&value[5..value.len() - 1], 4)
+        } else if value.starts_with("rgb(") && value.ends_with(')') {
+            (&value[4..value.len() - 1], 3)
+        } else {
+            return Err(invalid());
+        };
+
+        let arguments: Vec<&str> = arguments.split(',').map(|x| x.trim()).collect();
+
+        if arguments.len() != count {
+            return Err(format!(
+                "Expected {} color components, found {}",
+                count,
+                arguments.len()
+            ));
+        }
+
+        let mut color = [1.0; 4];
+
+        for (i, argument) in arguments.iter().enumerate() {
+            color[i] = if i < 3 {
+                argument.parse::<u8>().map_err(|_| {
+                    format!(
+                        "Expected a color component from 0 to 255, found '{}'",
+                        argument
+                    )
+                })? as f32
+                    / 255.0
+            } else {
+                argument
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|x| *x >= 0.0 && *x <= 1.0)
+                    .ok_or_else(|| {
+                        format!(
+                            "Expected an alpha value from 0 to 1.0, found '{}'",
+                            argument
+                        )
+                    })?
+            };
+        }
+
+        Ok(color)
+    }
+}
+
+/// Parses a whole number, optionally followed by `px`.
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    let number = if value.ends_with("px") {
+        &value[..value.len() - 2]
+    } else {
+        value
+    };
+
+    number
+        .parse::<T>()
+        .map_err(|_| format!("Expected a whole number, found '{}'", value))
+}
+
+/// Parses the name of a font, which must exist in the `assets` directory.
+fn parse_font_name(value: &str) -> Result<String, String> {
+    let font_name = parse_string(value)?;
+
+    TextWidget::find_font(&font_name)?;
+
+    Ok(font_name)
+}
+
+/// Parses a string, which may be surrounded by double or single quotes.
+fn parse_string(value: &str) -> Result<String, String> {
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')));
+
+    if quoted {
+        Ok(value[1..value.len() - 1].to_string())
+    } else if value.is_empty() {
+        Err("Expected a value".to_string())
+    } else {
+        Ok(value.to_string())
+    }
+}
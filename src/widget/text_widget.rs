@@ -14,20 +14,42 @@
 // limitations under the License.
 
 use piston_window::*;
+use std::path::PathBuf;
 
 use crate::core::callbacks::*;
 use crate::core::point::*;
 use crate::widget::config::*;
 use crate::widget::widget::*;
 
+/// The typed state of a `TextWidget`.  Its values are also stored in the widget's
+/// configuration, under the keys listed below.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextState {
+    /// The text to display, stored as `CONFIG_TEXT`.
+    pub text: String,
+
+    /// The color of the text, stored as `CONFIG_TEXT_COLOR`.
+    pub text_color: types::Color,
+
+    /// The name of the font file in the `assets` directory, stored as `CONFIG_FONT_NAME`.
+    pub font_name: String,
+
+    /// The size of the font, stored as `CONFIG_FONT_SIZE`.
+    pub font_size: u32,
+
+    /// The padding between the bounds of the widget and the text, stored as `CONFIG_PADDING`.
+    pub padding: u32,
+}
+
 /// This is the `TextWidget`, which draws a line of text on the screen.  This structure contains
 /// no accessable objects, they are all internal to `TextWidget`'s implementation.
 pub struct TextWidget {
     config: Configurable,
     callbacks: CallbackStore,
-    font_cache: Glyphs,
-    text: String,
-    font_size: u32,
+    factory: GfxFactory,
+    font_cache: Option<Glyphs>,
+    loaded_font_name: String,
+    state: TextState,
 }
 
 /// Implementation of the constructor for the `TextWidget`.  Creates a new text object to be
@@ -37,64 +59,157 @@ impl TextWidget {
     /// (which can be cloned), the name of the font (filename in the `assets` directory), the
     /// text to display, and the font size in which to use.
     pub fn new(factory: GfxFactory, font_name: String, text: String, font_size: u32) -> Self {
-        let assets = find_folder::Search::ParentsThenKids(3, 3)
-            .for_folder("assets")
-            .unwrap();
-        let ref font = assets.join(font_name.clone());
-        let glyphs = Glyphs::new(font, factory, TextureSettings::new()).unwrap();
+        let mut text_widget = Self::with_inherited_font(factory, text);
+
+        text_widget.set_font(font_name, font_size);
+        text_widget
+    }
+
+    /// Creates a new `TextWidget` object that displays the specified text, without setting a
+    /// font.  The font name and size are taken from the theme, or inherited from the widget's
+    /// parent, unless they are set with `set_font`.
+    pub fn with_inherited_font(factory: GfxFactory, text: String) -> Self {
+        let mut config = Configurable::new();
+
+        config.set(CONFIG_TEXT, text.clone());
 
         Self {
-            config: Configurable::new(),
+            config,
             callbacks: CallbackStore::new(),
-            font_cache: glyphs,
-            text,
-            font_size,
+            factory,
+            font_cache: None,
+            loaded_font_name: String::new(),
+            state: TextState {
+                text,
+                text_color: CONFIG_TEXT_COLOR.default_value(),
+                font_name: CONFIG_FONT_NAME.default_value(),
+                font_size: CONFIG_FONT_SIZE.default_value(),
+                padding: CONFIG_PADDING.default_value(),
+            },
         }
     }
 
+    /// Finds the font file with the specified name in the `assets` directory.  Returns an error
+    /// message if the `assets` directory or the font file cannot be found.  Style sheets and
+    /// layouts use this to reject unknown fonts before they are drawn.
+    pub(crate) fn find_font(font_name: &str) -> Result<PathBuf, String> {
+        let assets = find_folder::Search::ParentsThenKids(3, 3)
+            .for_folder("assets")
+            .map_err(|_| "Unable to find the assets folder".to_string())?;
+        let font = assets.join(font_name);
+
+        if font.is_file() {
+            Ok(font)
+        } else {
+            Err(format!(
+                "Font '{}' not found in the assets folder",
+                font_name
+            ))
+        }
+    }
+
+    /// Loads the font with the specified name from the `assets` directory.
+    pub(crate) fn load_font(factory: GfxFactory, font_name: &str) -> Result<Glyphs, String> {
+        let font = Self::find_font(font_name)?;
+
+        Glyphs::new(&font, factory, TextureSettings::new())
+            .map_err(|error| format!("Unable to load font '{}': {}", font_name, error))
+    }
+
     /// Sets the color of the text for this `Widget`.
     pub fn set_text_color(&mut self, color: types::Color) {
-        self.config()
-            .set(CONFIG_TEXT_COLOR, WidgetConfig::TextColor { color });
+        self.config().set(CONFIG_TEXT_COLOR, color);
+        self.state.text_color = color;
         self.invalidate();
     }
 
     /// Retrieves the color of the text for this `Widget`.
-    /// Defaults to black if not set.
-    pub fn get_text_color(&mut self) -> types::Color {
-        match self.config().get(CONFIG_TEXT_COLOR) {
-            Some(WidgetConfig::TextColor { color }) => color.clone(),
-            _ => [1.0; 4],
-        }
+    /// Defaults to blue if not set.
+    pub fn get_text_color(&self) -> types::Color {
+        self.state.text_color
     }
 
-    /// Changes the text, redraws after change.
+    /// Changes the text, redraws after change.  The text is stored under `CONFIG_TEXT`, so it
+    /// can be bound to a `Property` through the `WidgetStore`.
     pub fn set_text(&mut self, text: String) {
-        self.text = text.clone();
+        self.config().set(CONFIG_TEXT, text.clone());
+        self.state.text = text;
         self.invalidate();
     }
 
-    /// Function to draw the text.  Generates a context transformation to display the text based on
-    /// the point of origin's X and Y coordinates.  Since the text is drawn upwards from the point
-    /// of origin, the starting point is the lower left-hand corner of the widget.  (This may change
-    /// based on text justification, and other optional padding, once padding is introduced.)
+    /// Retrieves the text displayed by this `Widget`.
+    pub fn get_text(&self) -> String {
+        self.state.text.clone()
+    }
+
+    /// Sets the font used to draw the text: the name of the font file in the `assets` directory,
+    /// and the font size.
+    pub fn set_font(&mut self, font_name: String, font_size: u32) {
+        self.config().set(CONFIG_FONT_NAME, font_name.clone());
+        self.config().set(CONFIG_FONT_SIZE, font_size);
+        self.state.font_name = font_name;
+        self.state.font_size = font_size;
+        self.invalidate();
+    }
+
+    /// Function to draw the text.  Fills the widget with its color, or clears to black if no
+    /// color is set, then generates a context transformation to display the text based on the
+    /// point of origin's X and Y coordinates.  Since the text is drawn upwards from the point of
+    /// origin, the starting point is the lower left-hand corner of the widget, inset by the
+    /// widget's padding.  If the font cannot be loaded, only the fill is drawn.
     pub fn draw_text(&mut self, c: Context, g: &mut G2d) {
-        clear([0.0, 0.0, 0.0, 1.0], g);
+        let state = self.state.clone();
+
+        // Load the font when it is first drawn, and reload it if it has been changed, either
+        // explicitly, by a theme, or by a parent widget.
+        if self.loaded_font_name != state.font_name {
+            self.font_cache = match Self::load_font(self.factory.clone(), &state.font_name) {
+                Ok(font) => Some(font),
+                Err(message) => {
+                    eprintln!("Unable to draw text: {}", message);
+                    None
+                }
+            };
+            self.loaded_font_name = state.font_name.clone();
+        }
 
         let origin: Point = self.get_origin();
-        let transform = c
-            .transform
-            .trans(origin.x as f64, origin.y as f64 + self.get_size().h as f64);
-
-        text(
-            [0.0, 0.0, 1.0, 1.0],
-            self.font_size,
-            &self.text,
-            &mut self.font_cache,
-            transform,
-            g,
-        )
-        .unwrap();
+        let size: Size = self.get_size();
+        let padding = state.padding as f64;
+        let transform = c.transform.trans(
+            origin.x as f64 + padding,
+            origin.y as f64 + size.h as f64 - padding,
+        );
+
+        match self.config.get_ref(CONFIG_COLOR) {
+            Some(color) => rectangle(
+                *color,
+                [
+                    origin.x as f64,
+                    origin.y as f64,
+                    size.w as f64,
+                    size.h as f64,
+                ],
+                c.transform,
+                g,
+            ),
+            None => clear([0.0, 0.0, 0.0, 1.0], g),
+        }
+
+        if let Some(font_cache) = self.font_cache.as_mut() {
+            if text(
+                state.text_color,
+                state.font_size,
+                &state.text,
+                font_cache,
+                transform,
+                g,
+            )
+            .is_err()
+            {
+                eprintln!("Unable to draw text '{}'", state.text);
+            }
+        }
     }
 }
 
@@ -131,7 +246,7 @@ impl TextWidget {
 ///    text_widget.set_size(400, 40);
 ///    text_widget.set_color([0.75, 0.75, 1.0, 1.0]);
 ///    text_widget.set_text_color([0.0, 0.0, 1.0, 1.0]);
-///    prod.widget_store.add_widget(Box::new(text_widget));
+///    prod.widget_store.add_widget(Box::new(text_widget)).unwrap();
 /// # }
 /// ```
 impl Widget for TextWidget {
@@ -139,10 +254,34 @@ impl Widget for TextWidget {
         &mut self.config
     }
 
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
     fn callbacks(&mut self) -> &mut CallbackStore {
         &mut self.callbacks
     }
 
+    fn get_widget_type(&self) -> &'static str {
+        "text"
+    }
+
+    /// Updates the state when the text, text color, font or padding is changed by a theme, a
+    /// parent widget, a binding, an animation or a layout.
+    fn on_config_changed(&mut self, key_id: ConfigKeyId) {
+        if key_id == CONFIG_TEXT.id() {
+            self.state.text = self.config.get(CONFIG_TEXT);
+        } else if key_id == CONFIG_TEXT_COLOR.id() {
+            self.state.text_color = self.config.get(CONFIG_TEXT_COLOR);
+        } else if key_id == CONFIG_FONT_NAME.id() {
+            self.state.font_name = self.config.get(CONFIG_FONT_NAME);
+        } else if key_id == CONFIG_FONT_SIZE.id() {
+            self.state.font_size = self.config.get(CONFIG_FONT_SIZE);
+        } else if key_id == CONFIG_PADDING.id() {
+            self.state.padding = self.config.get(CONFIG_PADDING);
+        }
+    }
+
     /// Draws the contents of the widget.
     fn draw(&mut self, c: Context, g: &mut G2d) {
         // Draw the text.
@@ -152,3 +291,14 @@ impl Widget for TextWidget {
         self.clear_invalidate();
     }
 }
+
+/// Implementation of the typed state of the `TextWidget`.  The state is updated by the setters,
+/// and by `on_config_changed` when a theme, a parent widget, a binding, an animation or a layout
+/// changes the text or how it is drawn.
+impl StatefulWidget for TextWidget {
+    type State = TextState;
+
+    fn state(&self) -> TextState {
+        self.state.clone()
+    }
+}
@@ -0,0 +1,237 @@
+// Theme
+// Default configuration values for widgets, by widget type and state
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::widget::config::*;
+
+/// The interaction state of a `Widget`, used to select theme values.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum WidgetState {
+    /// The widget is not being interacted with.
+    Normal,
+
+    /// The mouse is over the widget.
+    Hover,
+
+    /// A mouse button was pressed over the widget, and has not yet been released.
+    Pressed,
+
+    /// The widget is disabled.
+    Disabled,
+
+    /// The widget has focus.
+    Focused,
+}
+
+/// Describes the widget that theme values are being resolved for.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StyleTarget {
+    /// The type name of the widget, as returned by `Widget::get_widget_type`.
+    pub widget_type: String,
+
+    /// The name of the widget, if it has one.
+    pub name: Option<String>,
+
+    /// The classes assigned to the widget.
+    pub classes: Vec<String>,
+
+    /// The current interaction state of the widget.
+    pub state: WidgetState,
+}
+
+/// Selects the widgets that a set of theme values apply to.  A selector with no criteria
+/// matches every widget in every state.
+#[derive(Clone, PartialEq, Debug)]
+pub struct StyleSelector {
+    widget_type: Option<String>,
+    name: Option<String>,
+    class: Option<String>,
+    state: Option<WidgetState>,
+}
+
+/// Implementation of the `StyleSelector`.
+impl StyleSelector {
+    /// Creates a selector that matches every widget.
+    pub fn any() -> Self {
+        Self {
+            widget_type: None,
+            name: None,
+            class: None,
+            state: None,
+        }
+    }
+
+    /// Creates a selector that matches widgets of the specified type name.
+    pub fn widget_type(widget_type: &str) -> Self {
+        Self::any().with_type(widget_type)
+    }
+
+    /// Restricts this selector to widgets of the specified type name.
+    pub fn with_type(mut self, widget_type: &str) -> Self {
+        self.widget_type = Some(widget_type.to_string());
+        self
+    }
+
+    /// Restricts this selector to the widget with the specified name.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Restricts this selector to widgets with the specified class.
+    pub fn with_class(mut self, class: &str) -> Self {
+        self.class = Some(class.to_string());
+        self
+    }
+
+    /// Restricts this selector to widgets in the specified state.
+    pub fn with_state(mut self, state: WidgetState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Indicates whether or not this selector matches the specified `StyleTarget`.
+    pub fn matches(&self, target: &StyleTarget) -> bool {
+        self.widget_type
+            .as_ref()
+            .map_or(true, |x| *x == target.widget_type)
+            && self
+                .name
+                .as_ref()
+                .map_or(true, |x| target.name.as_ref() == Some(x))
+            && self
+                .class
+                .as_ref()
+                .map_or(true, |x| target.classes.contains(x))
+            && self.state.map_or(true, |x| x == target.state)
+    }
+
+    /// Returns the specificity of this selector.  When several selectors match a widget, values
+    /// from more specific selectors take precedence: a name is more specific than a class or a
+    /// state, which are more specific than a widget type.
+    pub fn specificity(&self) -> u32 {
+        (if self.name.is_some() { 100 } else { 0 })
+            + (if self.class.is_some() { 10 } else { 0 })
+            + (if self.state.is_some() { 10 } else { 0 })
+            + (if self.widget_type.is_some() { 1 } else { 0 })
+    }
+}
+
+/// Internal structure containing the values for a single `StyleSelector`.
+struct ThemeRule {
+    selector: StyleSelector,
+    values: HashMap<ConfigKeyId, Rc<dyn Any>>,
+}
+
+/// This is the `Theme`, which provides default configuration values for widgets, such as colors,
+/// border widths, fonts and paddings.  Values are set for a `StyleSelector`, selecting widgets by
+/// their type and interaction state.
+///
+/// A `Theme` is attached to a `WidgetStore` (or to `Pushrod`), which resolves the theme values
+/// for every widget.  Values that are set explicitly on a widget always take precedence over its
+/// theme values.
+///
+/// Example usage:
+/// ```
+/// # use pushrod::core::widget_store::*;
+/// # use pushrod::widget::box_widget::*;
+/// # use pushrod::widget::config::*;
+/// # use pushrod::widget::theme::*;
+/// # use pushrod::widget::widget::*;
+/// # fn main() {
+///     let mut theme = Theme::new();
+///
+///     theme.set(StyleSelector::any(), CONFIG_COLOR, [0.9, 0.9, 0.9, 1.0]);
+///     theme.set(StyleSelector::widget_type("box"), CONFIG_BORDER_WIDTH, 2);
+///     theme.set(StyleSelector::widget_type("box").with_state(WidgetState::Hover),
+///         CONFIG_COLOR_BORDER, [0.0, 0.0, 1.0, 1.0]);
+///
+///     let mut widget_store = WidgetStore::new();
+///     let box_id = widget_store.add_widget(Box::new(BoxWidget::new())).unwrap();
+///
+///     widget_store.set_theme(theme);
+///     widget_store.set_hovered(box_id);
+///
+///     let config = widget_store.widgets[box_id as usize].widget.config();
+///     assert_eq!(config.get(CONFIG_COLOR), [0.9, 0.9, 0.9, 1.0]);
+///     assert_eq!(config.get(CONFIG_BORDER_WIDTH), 2);
+///     assert_eq!(config.get(CONFIG_COLOR_BORDER), [0.0, 0.0, 1.0, 1.0]);
+/// # }
+/// ```
+pub struct Theme {
+    rules: Vec<ThemeRule>,
+}
+
+/// Implementation of the `Theme`.
+impl Theme {
+    /// Creates a new, empty `Theme`.  Widgets use the default values of their configuration keys.
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Sets a theme value for the widgets matching the specified `StyleSelector`.
+    pub fn set<T: 'static>(&mut self, selector: StyleSelector, key: ConfigKey<T>, value: T) {
+        let value: Rc<dyn Any> = Rc::new(value);
+
+        match self.rules.iter_mut().find(|x| x.selector == selector) {
+            Some(rule) => {
+                rule.values.insert(key.id(), value);
+            }
+            None => {
+                let mut values = HashMap::new();
+
+                values.insert(key.id(), value);
+                self.rules.push(ThemeRule { selector, values });
+            }
+        }
+    }
+
+    /// Adds the values of another `Theme` to this one, in the order they were set, as though
+    /// they had been set on this `Theme` directly.
+    pub fn merge(&mut self, other: Theme) {
+        for rule in other.rules {
+            match self.rules.iter_mut().find(|x| x.selector == rule.selector) {
+                Some(existing) => existing.values.extend(rule.values),
+                None => self.rules.push(rule),
+            }
+        }
+    }
+
+    /// Resolves the theme values for the specified `StyleTarget`.  Values from more specific
+    /// selectors take precedence, and values set later take precedence over values set earlier
+    /// with the same specificity.
+    pub fn resolve(&self, target: &StyleTarget) -> HashMap<ConfigKeyId, Rc<dyn Any>> {
+        let mut rules: Vec<&ThemeRule> = self
+            .rules
+            .iter()
+            .filter(|x| x.selector.matches(target))
+            .collect();
+        let mut values = HashMap::new();
+
+        // The sort is stable, so rules of equal specificity keep the order they were added in.
+        rules.sort_by_key(|x| x.selector.specificity());
+
+        for rule in rules {
+            for (key_id, value) in rule.values.iter() {
+                values.insert(*key_id, value.clone());
+            }
+        }
+
+        values
+    }
+}
@@ -14,76 +14,204 @@
 // limitations under the License.
 
 use piston_window::*;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::cell::Cell;
+use std::rc::Rc;
 
 use crate::core::callbacks::*;
 use crate::core::point::*;
+use crate::core::scheduler::*;
+use crate::widget::builder::*;
 use crate::widget::config::*;
+use crate::widget::layout::*;
 use crate::widget::widget::*;
 
-/// This is the `TimerWidget`.  It contains no base widget, it only contains a start and end
-/// time,
+/// The typed state of a `TimerWidget`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TimerState {
+    /// The timeout in milliseconds.
+    pub timeout: u64,
+
+    /// The number of times the timer fires before it stops.
+    pub repeat: TimerRepeat,
+
+    /// Whether or not the timer is paused.
+    pub paused: bool,
+
+    /// The number of times the timer has fired since it was last reset.
+    pub fire_count: u32,
+}
+
+/// This is the `TimerWidget`.  It contains no base widget, it is a thin wrapper around an
+/// interval in the `Scheduler` of the `WidgetStore` it is added to, which is the scheduler of
+/// `Pushrod` and is ticked during the update phase of the run loop.  The timer only runs once it
+/// has been added to a store, and uses the store's clock, such as the one given to
+/// `Pushrod::with_clock`.
 pub struct TimerWidget {
     config: Configurable,
     callbacks: CallbackStore,
-    enabled: bool,
-    initiated: u64,
-    timeout: u64,
+    scheduler: Option<SharedScheduler>,
+    timer_id: Option<TimerId>,
+    state: TimerState,
+    pending_fires: Rc<Cell<u32>>,
+    parent_enabled: bool,
     timeout_function: Box<Fn() -> ()>,
 }
 
-/// Helper function that returns the current time in milliseconds since the `UNIX_EPOCH`.  This
-/// function is the equivalent of a `System.currentTimeMillis()` in Java.
-fn time_ms() -> u64 {
-    let since_the_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-
-    (since_the_epoch.as_secs() * 1_000) + (since_the_epoch.subsec_nanos() / 1_000_000) as u64
-}
-
 /// Implementation of the constructor for the `TimerWidget`.  Timer widgets are not accessible
 /// on the screen, so they have an origin of 0x0 and width of 0x0.
 ///
 /// The timer provides a simple way to call a callback function after a certain amount of time
-/// has passed.  Upon instantiation, the timer is enabled.
-///
-/// Here are a few limitations of the timer as it currently stands:
+/// has passed.  Upon instantiation, the timer is enabled, and repeats until it is disabled.
+/// Use `set_repeat` to fire the timer only once, or a fixed number of times.
 ///
-/// - Timer cannot be paused; it is enabled or disabled, and the timer resets when enabled.
-/// - Timer is called when the screen refreshes, so slower FPS settings will affect the timer.
+/// Disabling and re-enabling the timer resets it, while `pause` and `resume` keep the time
+/// that has already elapsed.  A timer in a `WidgetStore` also stops while its parent, or any
+/// other ancestor, is disabled, and is reset when they are enabled again.  If the run loop
+/// updates the timer late, the timer fires once for every timeout that has elapsed, so the fire
+/// count stays accurate.
 impl TimerWidget {
     /// Constructor, creates a new `TimerWidget` struct with an empty timeout function.
+    ///
+    /// Example usage, using a `ManualClock` to trigger the timer:
+    /// ```
+    /// # use pushrod::core::clock::*;
+    /// # use pushrod::core::scheduler::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # use std::cell::{Cell, RefCell};
+    /// # use std::rc::Rc;
+    /// # fn main() {
+    ///    let clock = Rc::new(ManualClock::new());
+    ///    let scheduler = Rc::new(RefCell::new(Scheduler::with_clock(clock.clone())));
+    ///    let mut widget_store = WidgetStore::with_scheduler(scheduler.clone());
+    ///    let fired = Rc::new(Cell::new(0));
+    ///    let fired_timeout = fired.clone();
+    ///    let mut timer_widget = TimerWidget::new();
+    ///
+    ///    timer_widget.set_timeout(1000);
+    ///    timer_widget.on_timeout(Box::new(move || fired_timeout.set(fired_timeout.get() + 1)));
+    ///    widget_store.add_widget(Box::new(timer_widget)).unwrap();
+    ///
+    ///    // Done by the run loop during the update phase.
+    ///    clock.advance(999);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///    assert_eq!(fired.get(), 0);
+    ///
+    ///    clock.advance(1);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///    assert_eq!(fired.get(), 1);
+    /// # }
+    /// ```
     pub fn new() -> Self {
         Self {
             config: Configurable::new(),
             callbacks: CallbackStore::new(),
-            enabled: true,
-            initiated: time_ms(),
-            timeout: 0,
+            scheduler: None,
+            timer_id: None,
+            state: TimerState {
+                timeout: 0,
+                repeat: TimerRepeat::Forever,
+                paused: false,
+                fire_count: 0,
+            },
+            pending_fires: Rc::new(Cell::new(0)),
+            parent_enabled: true,
             timeout_function: Box::new(|| {}),
         }
     }
 
-    // Called to check the time since initiation, and call the timeout function when a timer has
-    // been triggered.
-    fn tick(&mut self) {
-        if !self.enabled {
+    /// Creates a `WidgetBuilder` for a new `TimerWidget`.
+    pub fn builder() -> WidgetBuilder<Self> {
+        WidgetBuilder::new(Self::new())
+    }
+
+    // Cancels the current interval in the scheduler, if there is one.
+    fn cancel(&mut self) {
+        if let (Some(scheduler), Some(timer_id)) = (&self.scheduler, self.timer_id.take()) {
+            scheduler.borrow_mut().cancel(timer_id);
+        }
+    }
+
+    // Cancels the current interval, resets the fire count, and schedules a new interval with
+    // the current timeout if the timer has been added to a `WidgetStore`, and the timer and all
+    // of its parents are enabled.  The scheduled callback only counts the fires; the timeout
+    // functions are called from `update`, where the widget ID is known.
+    fn reschedule(&mut self) {
+        self.cancel();
+
+        self.state.fire_count = 0;
+        self.pending_fires.set(0);
+
+        if !self.is_enabled() || !self.parent_enabled {
             return;
         }
 
-        let elapsed = time_ms() - self.initiated;
+        if let Some(scheduler) = &self.scheduler {
+            let mut scheduler = scheduler.borrow_mut();
+            let pending_fires = self.pending_fires.clone();
+            let timer_id = scheduler.schedule(
+                self.state.timeout,
+                self.state.repeat,
+                Box::new(move || pending_fires.set(pending_fires.get() + 1)),
+            );
 
-        if elapsed > self.timeout {
-            self.initiated = time_ms();
-            (self.timeout_function)();
+            if self.state.paused {
+                scheduler.pause(timer_id);
+            }
+
+            self.timer_id = Some(timer_id);
         }
     }
 
-    /// Enables or disables the timer.  When disabled, the timer will not initiate the callback
-    /// function.  When re-enabled, the initiation time resets, so the timer will reset back to
-    /// zero, effectively resetting the entire timer.
-    pub fn set_enabled(&mut self, enabled: bool) {
-        self.enabled = enabled;
-        self.initiated = time_ms();
+    /// Pauses the timer.  Unlike disabling the timer, the time that has already elapsed is kept,
+    /// and the timer continues from that point when `resume` is called.
+    pub fn pause(&mut self) {
+        self.state.paused = true;
+
+        if let (Some(scheduler), Some(timer_id)) = (&self.scheduler, self.timer_id) {
+            scheduler.borrow_mut().pause(timer_id);
+        }
+    }
+
+    /// Resumes a paused timer.
+    pub fn resume(&mut self) {
+        self.state.paused = false;
+
+        if let (Some(scheduler), Some(timer_id)) = (&self.scheduler, self.timer_id) {
+            scheduler.borrow_mut().resume(timer_id);
+        }
+    }
+
+    /// Indicates whether or not the timer is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused
+    }
+
+    /// Indicates whether or not the timer is still running: it is in a `WidgetStore`, it is
+    /// enabled, and has not yet fired the number of times specified by `set_repeat`.
+    pub fn is_running(&self) -> bool {
+        match (&self.scheduler, self.timer_id) {
+            (Some(scheduler), Some(timer_id)) => scheduler.borrow().is_scheduled(timer_id),
+            _ => false,
+        }
+    }
+
+    /// Retrieves the number of milliseconds remaining until the timer next fires.  Returns 0 if
+    /// the timer is no longer running.
+    pub fn get_remaining(&self) -> u64 {
+        match (&self.scheduler, self.timer_id) {
+            (Some(scheduler), Some(timer_id)) => {
+                scheduler.borrow().remaining(timer_id).unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Retrieves the number of times the timer has fired since it was last reset.
+    pub fn get_fire_count(&self) -> u32 {
+        self.state.fire_count
     }
 
     /// Sets the closure function for the timer when a timeout has been triggered.  This closure
@@ -92,11 +220,78 @@ impl TimerWidget {
         self.timeout_function = timeout_function;
     }
 
+    /// Sets the closure action to be performed when the timer fires.  The closure receives the
+    /// widget ID of the timer and the number of times the timer has fired, starting at 1.
+    pub fn on_timer_fired(&mut self, callback: CountCallback) {
+        self.callbacks()
+            .put(CALLBACK_TIMER_FIRED, CallbackTypes::CountCallback { callback });
+    }
+
     /// Sets the timeout in milliseconds for this timer.  Will trigger a call to the function
     /// set in `on_timeout` when triggered, and will continue to call that function until this
-    /// timer is disabled by using `self.set_enabled(false)`.
+    /// timer is disabled by using `self.set_enabled(false)`.  Resets the timer.
     pub fn set_timeout(&mut self, timeout: u64) {
-        self.timeout = timeout;
+        self.state.timeout = timeout;
+        self.reschedule();
+    }
+
+    /// Sets the number of times the timer fires before it stops.  Defaults to
+    /// `TimerRepeat::Forever`.  Resets the timer.
+    ///
+    /// Example usage:
+    /// ```
+    /// # use pushrod::core::clock::*;
+    /// # use pushrod::core::scheduler::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # use std::cell::{Cell, RefCell};
+    /// # use std::rc::Rc;
+    /// # fn main() {
+    ///    let clock = Rc::new(ManualClock::new());
+    ///    let scheduler = Rc::new(RefCell::new(Scheduler::with_clock(clock.clone())));
+    ///    let mut widget_store = WidgetStore::with_scheduler(scheduler.clone());
+    ///    let last_fired = Rc::new(Cell::new((0, 0)));
+    ///    let last_fired_callback = last_fired.clone();
+    ///    let mut timer_widget = TimerWidget::new();
+    ///
+    ///    timer_widget.set_timeout(100);
+    ///    timer_widget.set_repeat(TimerRepeat::Times(3));
+    ///    timer_widget.on_timer_fired(Box::new(move |widget_id, count| {
+    ///        last_fired_callback.set((widget_id, count));
+    ///    }));
+    ///
+    ///    let timer_id = widget_store.add_widget(Box::new(timer_widget)).unwrap();
+    ///
+    ///    // Pausing keeps the elapsed time.
+    ///    clock.advance(60);
+    ///    widget_store.get_as_mut::<TimerWidget>(timer_id).unwrap().pause();
+    ///    clock.advance(1000);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///
+    ///    let timer_widget = widget_store.get_as_mut::<TimerWidget>(timer_id).unwrap();
+    ///    assert_eq!(timer_widget.get_remaining(), 40);
+    ///    timer_widget.resume();
+    ///
+    ///    // A late update fires once for each elapsed timeout.
+    ///    clock.advance(140);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///    assert_eq!(last_fired.get(), (timer_id, 2));
+    ///
+    ///    // The timer stops after the third fire.
+    ///    clock.advance(1000);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///
+    ///    let timer_widget = widget_store.get_as::<TimerWidget>(timer_id).unwrap();
+    ///    assert_eq!(timer_widget.get_fire_count(), 3);
+    ///    assert!(!timer_widget.is_running());
+    /// # }
+    /// ```
+    pub fn set_repeat(&mut self, repeat: TimerRepeat) {
+        self.state.repeat = repeat;
+        self.reschedule();
     }
 }
 
@@ -121,14 +316,120 @@ impl Widget for TimerWidget {
         &mut self.config
     }
 
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
     fn callbacks(&mut self) -> &mut CallbackStore {
         &mut self.callbacks
     }
 
-    /// Timer is always invalidated, this way, the tick function is always called on each
-    /// screen refresh.
-    fn is_invalidated(&mut self) -> bool {
-        true
+    fn get_widget_type(&self) -> &'static str {
+        "timer"
+    }
+
+    /// Enables or disables the timer.  When disabled, the timer will not initiate the callback
+    /// function.  When re-enabled, the initiation time resets, so the timer will reset back to
+    /// zero, effectively resetting the entire timer.
+    fn set_enabled(&mut self, enabled: bool) {
+        self.config().set(CONFIG_ENABLED, enabled);
+        self.reschedule();
+    }
+
+    /// Resets the timer in the same way as `set_enabled` when it is enabled or disabled by a
+    /// binding, a theme, an animation or a command.
+    ///
+    /// Example usage:
+    /// ```
+    /// # use pushrod::core::property::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # fn main() {
+    ///    let mut widget_store = WidgetStore::new();
+    ///    let mut timer_widget = TimerWidget::new();
+    ///    let running = Property::new(true);
+    ///
+    ///    timer_widget.set_timeout(1000);
+    ///
+    ///    let timer_id = widget_store.add_widget(Box::new(timer_widget)).unwrap();
+    ///
+    ///    widget_store.bind(timer_id, CONFIG_ENABLED, &running);
+    ///    running.set(false);
+    ///    widget_store.update_bindings();
+    ///    assert!(!widget_store.get_as::<TimerWidget>(timer_id).unwrap().is_running());
+    ///
+    ///    running.set(true);
+    ///    widget_store.update_bindings();
+    ///    assert!(widget_store.get_as::<TimerWidget>(timer_id).unwrap().is_running());
+    /// # }
+    /// ```
+    fn on_config_changed(&mut self, key_id: ConfigKeyId) {
+        if key_id == CONFIG_ENABLED.id() {
+            self.reschedule();
+        }
+    }
+
+    /// Stops the timer while a parent is disabled, and resets it when its parents are enabled
+    /// again, in the same way as `set_enabled`.
+    ///
+    /// Example usage:
+    /// ```
+    /// # use pushrod::core::clock::*;
+    /// # use pushrod::core::scheduler::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::widget::*;
+    /// # use pushrod::widget::timer_widget::*;
+    /// # use std::cell::RefCell;
+    /// # use std::rc::Rc;
+    /// # fn main() {
+    ///    let clock = Rc::new(ManualClock::new());
+    ///    let scheduler = Rc::new(RefCell::new(Scheduler::with_clock(clock.clone())));
+    ///    let mut widget_store = WidgetStore::with_scheduler(scheduler.clone());
+    ///    let mut timer_widget = TimerWidget::new();
+    ///
+    ///    timer_widget.set_timeout(1000);
+    ///
+    ///    let parent_id = widget_store.add_widget(Box::new(BaseWidget::new())).unwrap();
+    ///    let timer_id = widget_store
+    ///        .add_widget_to_parent(Box::new(timer_widget), parent_id)
+    ///        .unwrap();
+    ///
+    ///    widget_store.get_widget_mut(parent_id).unwrap().set_enabled(false);
+    ///    widget_store.update_all_widgets(0.0);
+    ///    clock.advance(1000);
+    ///    scheduler.borrow_mut().tick();
+    ///    widget_store.update_all_widgets(0.0);
+    ///
+    ///    let timer = widget_store.get_as::<TimerWidget>(timer_id).unwrap();
+    ///    assert!(!timer.is_running());
+    ///    assert_eq!(timer.get_fire_count(), 0);
+    /// # }
+    /// ```
+    fn on_parent_enabled_changed(&mut self, _widget_id: i32, parent_enabled: bool) {
+        self.parent_enabled = parent_enabled;
+        self.reschedule();
+    }
+
+    /// Schedules the timer in the `Scheduler` of the `WidgetStore` it has been added to.
+    fn on_scheduler_attached(&mut self, scheduler: &SharedScheduler) {
+        self.cancel();
+        self.scheduler = Some(scheduler.clone());
+        self.reschedule();
+    }
+
+    /// Cancels the timer when it is removed from its `WidgetStore`.  It is scheduled again if it
+    /// is restored.
+    fn on_removed(&mut self, _widget_id: i32) {
+        self.cancel();
+    }
+
+    /// Saves the timeout, which is read by the `timer` constructor in the `WidgetRegistry`.
+    fn get_layout_properties(&self) -> Vec<(String, LayoutValue)> {
+        vec![(
+            "timeout".to_string(),
+            LayoutValue::Number(self.state.timeout as f64),
+        )]
     }
 
     /// Origin is always set to X/Y at points 0x0.
@@ -141,9 +442,39 @@ impl Widget for TimerWidget {
         make_unsized()
     }
 
-    /// Does not draw anything - only calls the timer `tick()` function to increment the
-    /// timer.
+    /// Calls the timeout functions once for each time the timer has fired in the `Scheduler`
+    /// since the last update.
+    fn update(&mut self, widget_id: i32, _dt: f64) {
+        for _ in 0..self.pending_fires.replace(0) {
+            self.state.fire_count += 1;
+            (self.timeout_function)();
+            self.perform_count_callback(CALLBACK_TIMER_FIRED, widget_id, self.state.fire_count);
+        }
+    }
+
+    /// Does not draw anything, as timers are invisible.
     fn draw(&mut self, _context: Context, _graphics: &mut G2d) {
-        self.tick();
+        self.clear_invalidate();
+    }
+}
+
+/// Implementation of the typed state of the `TimerWidget`.
+impl StatefulWidget for TimerWidget {
+    type State = TimerState;
+
+    fn state(&self) -> TimerState {
+        self.state.clone()
+    }
+}
+
+/// Cancels the timer in the `Scheduler` when the widget is dropped.  The scheduler is not borrowed
+/// if it is ticking, as the timer's callback cannot fire again once the widget is gone.
+impl Drop for TimerWidget {
+    fn drop(&mut self) {
+        if let (Some(scheduler), Some(timer_id)) = (&self.scheduler, self.timer_id) {
+            if let Ok(mut scheduler) = scheduler.try_borrow_mut() {
+                scheduler.cancel(timer_id);
+            }
+        }
     }
 }
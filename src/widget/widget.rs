@@ -13,10 +13,15 @@
 // limitations under the License.
 
 use piston_window::*;
+use std::any::Any;
 
 use crate::core::callbacks::*;
 use crate::core::point::*;
+use crate::core::scheduler::*;
+use crate::core::widget_store::*;
+use crate::widget::builder::*;
 use crate::widget::config::*;
+use crate::widget::layout::*;
 
 /// Implementable trait that is used by every `Widget`.  These are the public methods,
 /// and a function _may_ override them.
@@ -32,7 +37,10 @@ use crate::widget::config::*;
 /// If you want a blank base widget, refer to the `BaseWidget`, which will create a
 /// base widget that paints the contents of its bounds with whatever color has been
 /// specified with `set_color`.
-pub trait Widget {
+///
+/// `AsAny` is implemented automatically for every widget, which allows a `Widget` trait object
+/// to be downcast to its concrete type, as done by `WidgetStore::get_as_mut`.
+pub trait Widget: AsAny {
     /// Retrieves the configuration HashMap that stores the configuration list of settings
     /// for this widget.
     ///
@@ -71,6 +79,10 @@ pub trait Widget {
     ///     &mut self.config
     ///   }
     ///
+    ///   fn get_config(&self) -> &Configurable {
+    ///     &self.config
+    ///   }
+    ///
     ///   fn callbacks(&mut self) -> &mut CallbackStore {
     ///     &mut self.callbacks
     ///   }
@@ -86,14 +98,23 @@ pub trait Widget {
     /// that stores the configs.
     fn config(&mut self) -> &mut Configurable;
 
+    /// Retrieves the same configuration as `config`, without requiring mutable access, so that
+    /// it can be read while the widget is borrowed immutably, such as when a layout is saved.
+    fn get_config(&self) -> &Configurable;
+
     /// Returns the `CallbackStore` for this `Widget`.  This contains a set of callbacks that only
     /// apply to this `Widget`.
     fn callbacks(&mut self) -> &mut CallbackStore;
 
+    /// Retrieves the type name of this widget, which is used to select `Theme` values for the
+    /// widget.  Override this in your own widgets to give them a distinct type name.
+    fn get_widget_type(&self) -> &'static str {
+        "widget"
+    }
+
     /// Indicates that a widget needs to be redrawn/refreshed.
     fn invalidate(&mut self) {
-        self.config()
-            .set(CONFIG_INVALIDATE, WidgetConfig::Invalidate {});
+        self.config().set(CONFIG_INVALIDATE, true);
     }
 
     /// Clears the invalidation flag.
@@ -103,87 +124,128 @@ pub trait Widget {
 
     /// Checks to see whether or not the widget needs to be redrawn/refreshed.
     fn is_invalidated(&mut self) -> bool {
-        self.config().contains_key(CONFIG_INVALIDATE)
+        self.config().get(CONFIG_INVALIDATE)
     }
 
     /// Sets the `Point` of origin for this widget, given the X and Y origin points.  Invalidates the widget afterward.
     fn set_origin(&mut self, x: i32, y: i32) {
-        self.config().set(
-            CONFIG_ORIGIN,
-            WidgetConfig::Origin {
-                point: Point { x, y },
-            },
-        );
+        self.config().set(CONFIG_ORIGIN, Point { x, y });
         self.invalidate();
     }
 
     /// Retrieves the `Point` of origin for this object.
     /// Defaults to origin (0, 0) if not set.
     fn get_origin(&mut self) -> Point {
-        match self.config().get(CONFIG_ORIGIN) {
-            Some(WidgetConfig::Origin { ref point }) => point.clone(),
-            None => make_origin_point(),
-            _ => make_origin_point(),
-        }
+        self.config().get(CONFIG_ORIGIN)
     }
 
     /// Sets the `Size` for this widget, given a width and height.  Invalidates the widget afterward.
     fn set_size(&mut self, w: i32, h: i32) {
-        self.config().set(
-            CONFIG_SIZE,
-            WidgetConfig::Size {
-                size: crate::core::point::Size { w, h },
-            },
-        );
+        self.config().set(CONFIG_SIZE, Size { w, h });
         self.invalidate();
     }
 
     /// Retrieves the `Size` bounds for this widget.
     /// Defaults to size (0, 0) if not set.
     fn get_size(&mut self) -> crate::core::point::Size {
-        match self.config().get(CONFIG_SIZE) {
-            Some(WidgetConfig::Size { ref size }) => size.clone(),
-            _ => make_unsized(),
-        }
+        self.config().get(CONFIG_SIZE)
     }
 
     /// Sets the color for this widget.  Invalidates the widget afterward.
     fn set_color(&mut self, color: types::Color) {
-        self.config()
-            .set(CONFIG_COLOR, WidgetConfig::Color { color });
+        self.config().set(CONFIG_COLOR, color);
         self.invalidate();
     }
 
     /// Retrieves the color of this widget.
     /// Defaults to white color `[1.0; 4]` if not set.
     fn get_color(&mut self) -> types::Color {
-        if self.config().contains_key(CONFIG_COLOR) {
-            match self.config().get(CONFIG_COLOR) {
-                Some(WidgetConfig::Color { ref color }) => [color[0], color[1], color[2], color[3]],
-                None => [1.0; 4],
-                _ => [1.0; 4],
-            }
-        } else {
-            [1.0; 4]
-        }
+        self.config().get(CONFIG_COLOR)
     }
 
     /// Indicates to the underlying drawing mechanism as to whether or not this `Widget` needs to
     /// have drawing clipping automatically applied.
     fn set_autoclip(&mut self, clip: bool) {
-        self.config()
-            .set(CONFIG_AUTOCLIP, WidgetConfig::Autoclip { clip });
+        self.config().set(CONFIG_AUTOCLIP, clip);
         self.invalidate();
     }
 
     /// Retrieves the auto clip flag.
     fn get_autoclip(&mut self) -> bool {
-        match self.config().get(CONFIG_AUTOCLIP) {
-            Some(WidgetConfig::Autoclip { ref clip }) => clip.clone(),
-            _ => false,
+        self.config().get(CONFIG_AUTOCLIP)
+    }
+
+    /// Sets the name of this `Widget`, used to look it up with `WidgetStore::find_by_name`, and
+    /// to select it in a `Theme`.  An empty name removes the name.  Names must be unique within
+    /// a `WidgetStore`, so once a widget has been added to a store, its name does not change
+    /// with this function: use `WidgetStore::set_widget_name` to rename it.
+    fn set_name(&mut self, name: &str) {
+        self.config().set(CONFIG_NAME, name.to_string());
+    }
+
+    /// Retrieves the name of this `Widget`, or `None` if it has no name.
+    fn get_name(&mut self) -> Option<String> {
+        Some(self.config().get(CONFIG_NAME)).filter(|x| !x.is_empty())
+    }
+
+    /// Adds a class to this `Widget`, used to look it up with `WidgetStore::find_by_class`, and
+    /// to select it in a `Theme`.
+    fn add_class(&mut self, class: &str) {
+        let mut classes = self.get_classes();
+
+        if !classes.iter().any(|x| x == class) {
+            classes.push(class.to_string());
+            self.config().set(CONFIG_CLASSES, classes);
+        }
+    }
+
+    /// Removes a class from this `Widget`.
+    fn remove_class(&mut self, class: &str) {
+        let mut classes = self.get_classes();
+
+        if classes.iter().any(|x| x == class) {
+            classes.retain(|x| x != class);
+            self.config().set(CONFIG_CLASSES, classes);
         }
     }
 
+    /// Indicates whether or not this `Widget` has the specified class.
+    fn has_class(&mut self, class: &str) -> bool {
+        self.config()
+            .get_ref(CONFIG_CLASSES)
+            .map_or(false, |x| x.iter().any(|x| x == class))
+    }
+
+    /// Retrieves the classes of this `Widget`.
+    fn get_classes(&mut self) -> Vec<String> {
+        self.config().get(CONFIG_CLASSES)
+    }
+
+    /// Shows or hides this `Widget`.  Hidden widgets and their children are not drawn, and do
+    /// not receive mouse events.
+    fn set_visible(&mut self, visible: bool) {
+        self.config().set(CONFIG_VISIBLE, visible);
+        self.invalidate();
+    }
+
+    /// Indicates whether or not this `Widget` is visible.  Widgets are visible by default.
+    fn is_visible(&mut self) -> bool {
+        self.config().get(CONFIG_VISIBLE)
+    }
+
+    /// Enables or disables this `Widget`.  Disabled widgets and their children do not receive
+    /// callbacks from the run loop, and cannot take focus.  Their themes are resolved using the
+    /// `WidgetState::Disabled` state, so they can be drawn with a disabled look.
+    fn set_enabled(&mut self, enabled: bool) {
+        self.config().set(CONFIG_ENABLED, enabled);
+        self.invalidate();
+    }
+
+    /// Indicates whether or not this `Widget` is enabled.  Widgets are enabled by default.
+    fn is_enabled(&mut self) -> bool {
+        self.config().get(CONFIG_ENABLED)
+    }
+
     // Callbacks
 
     /// Performs a callback stored in the `CallbackStore` for this `Widget`, but only for the
@@ -206,6 +268,16 @@ pub trait Widget {
         }
     }
 
+    /// Performs a callback stored in the `CallbackStore` for this `Widget`, but only for the
+    /// `CallbackTypes::CountCallback` enum type.  If the callback does not exist, or is not
+    /// defined properly, it will be silently dropped and ignored.
+    fn perform_count_callback(&mut self, callback_id: u32, widget_id: i32, count: u32) {
+        match self.callbacks().get(callback_id) {
+            CallbackTypes::CountCallback { callback } => callback(widget_id, count),
+            _ => (),
+        }
+    }
+
     // Callback Triggers
 
     /// Called when a mouse enters the bounds of the widget.  Includes the widget ID.  Only override
@@ -266,9 +338,150 @@ pub trait Widget {
         );
     }
 
+    // Composition
+
+    /// Called once when the widget is added to a `WidgetStore`, with the ID it was assigned.
+    /// Override this to build a composite widget: add child widgets to the `widget_store` with
+    /// `add_widget_to_parent`, using `widget_id` as the parent, and keep their IDs to update
+    /// them later.  The children are then moved with `WidgetStore::move_widget`, hidden with
+    /// `set_visible`, and removed with `WidgetStore::remove_widget` together with this widget.
+    ///
+    /// While this is called, this widget is not accessible through the `widget_store`.  It is
+    /// stored as a placeholder with the same type, name, classes, visibility, enabled state,
+    /// bounds and inheritable values, so children inherit from this widget, and names used by
+    /// this widget are seen as taken.
+    ///
+    /// Example usage:
+    /// ```
+    /// # use piston_window::*;
+    /// # use pushrod::core::callbacks::*;
+    /// # use pushrod::core::widget_store::*;
+    /// # use pushrod::widget::box_widget::*;
+    /// # use pushrod::widget::config::*;
+    /// # use pushrod::widget::widget::*;
+    /// // A box containing a smaller indicator box.
+    /// struct IndicatorWidget {
+    ///     config: Configurable,
+    ///     callbacks: CallbackStore,
+    ///     indicator_id: i32,
+    /// }
+    ///
+    /// impl Widget for IndicatorWidget {
+    ///     fn config(&mut self) -> &mut Configurable {
+    ///         &mut self.config
+    ///     }
+    ///
+    ///     fn get_config(&self) -> &Configurable {
+    ///         &self.config
+    ///     }
+    ///
+    ///     fn callbacks(&mut self) -> &mut CallbackStore {
+    ///         &mut self.callbacks
+    ///     }
+    ///
+    ///     fn build(&mut self, widget_id: i32, widget_store: &mut WidgetStore) {
+    ///         let origin = self.get_origin();
+    ///         let mut indicator = BoxWidget::new();
+    ///
+    ///         indicator.set_origin(origin.x + 4, origin.y + 4);
+    ///         indicator.set_size(8, 8);
+    ///         self.indicator_id = widget_store
+    ///             .add_widget_to_parent(Box::new(indicator), widget_id)
+    ///             .unwrap();
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    ///     let mut widget_store = WidgetStore::new();
+    ///     let mut widget = IndicatorWidget {
+    ///         config: Configurable::new(),
+    ///         callbacks: CallbackStore::new(),
+    ///         indicator_id: -1,
+    ///     };
+    ///
+    ///     widget.set_origin(10, 10);
+    ///     widget.set_size(16, 16);
+    ///
+    ///     let widget_id = widget_store.add_widget(Box::new(widget)).unwrap();
+    ///     let indicator_id = widget_store.get_as::<IndicatorWidget>(widget_id).unwrap().indicator_id;
+    ///
+    ///     assert_eq!(widget_store.get_children_of(widget_id), vec![indicator_id]);
+    ///
+    ///     widget_store.move_widget(widget_id, 100, 50).unwrap();
+    ///     assert_eq!(widget_store.get_widget_mut(indicator_id).unwrap().get_origin().x, 104);
+    ///
+    ///     widget_store.remove_widget(widget_id);
+    ///     assert!(widget_store.get_descendants_of(0).is_empty());
+    /// # }
+    /// ```
+    fn build(&mut self, _widget_id: i32, _widget_store: &mut WidgetStore) {}
+
+    // Layout routines
+
+    /// Retrieves the properties of the widget that are saved to a layout file in addition to its
+    /// configuration values, such as settings that are not stored in the `Configurable`.  When
+    /// the layout is loaded, these properties are passed to the widget's constructor in the
+    /// `WidgetRegistry`.  Configuration values with a custom `ConfigKey` are only saved if they
+    /// are written here, as a property with the key's name.
+    fn get_layout_properties(&self) -> Vec<(String, LayoutValue)> {
+        Vec::new()
+    }
+
+    // Lifecycle routines
+
+    /// Called when the widget is added to a `WidgetStore`, before `on_added`, with the
+    /// `Scheduler` of the store.  Widgets that schedule timeouts or intervals keep the scheduler,
+    /// so their callbacks run on the same clock as the rest of the application.
+    fn on_scheduler_attached(&mut self, _scheduler: &SharedScheduler) {}
+
+    /// Called when a configuration value of the widget changes without going through one of its
+    /// setters: when its theme or inherited values change, or when a value is written by a
+    /// `Property` binding, an animation, a command, a layout or a `WidgetBuilder`.  Widgets that
+    /// keep a typed state, such as the `BoxWidget`, override this to update it, as do widgets
+    /// that act on a setting, such as a `TimerWidget` that is disabled.
+    fn on_config_changed(&mut self, _key_id: ConfigKeyId) {}
+
+    /// Called when the widget has been added to a `WidgetStore`, with the ID it was assigned,
+    /// before `build` is called.
+    fn on_added(&mut self, _widget_id: i32) {}
+
+    /// Called when the widget is removed from its `WidgetStore` by `remove_widget`.  Descendants
+    /// of the removed widget are removed first.  Override this to release resources held by the
+    /// widget.
+    fn on_removed(&mut self, _widget_id: i32) {}
+
+    /// Called when the widget is moved to another parent with `WidgetStore::set_parent`, with the
+    /// IDs of its old and new parents.
+    fn on_parent_changed(&mut self, _widget_id: i32, _old_parent_id: i32, _new_parent_id: i32) {}
+
+    /// Called during the update phase of the run loop when the size of the widget has changed,
+    /// with its old and new sizes.  Override this to lay out contents that depend on the size of
+    /// the widget.
+    fn on_resized(&mut self, _widget_id: i32, _old_size: &Size, _new_size: &Size) {}
+
+    /// Called during the update phase of the run loop, before `update`, when the widget's parent
+    /// or one of its other ancestors is disabled or enabled.  `parent_enabled` is `false` if any
+    /// ancestor of the widget is disabled.  Override this to stop work, such as timers, that
+    /// should not run while the widget is effectively disabled.
+    fn on_parent_enabled_changed(&mut self, _widget_id: i32, _parent_enabled: bool) {}
+
+    // Update routines
+
+    /// Called by the `Pushrod` run loop on every update event, before any drawing takes place.
+    /// Includes the widget ID, and `dt`, the number of seconds that have elapsed since the
+    /// previous update.
+    ///
+    /// Override this if your widget needs to run logic every frame, such as checking for an
+    /// elapsed timer, animating, or polling state.  Updating does not cause a repaint: call
+    /// `invalidate()` if the update changed what the widget draws, and keep `draw` a pure render
+    /// of the widget's current state.
+    fn update(&mut self, _widget_id: i32, _dt: f64) {}
+
     // Draw routines
 
     /// Draws the contents of the widget, provided a `piston2d` `Context` and `G2d` object.
+    /// Drawing should only render the widget's current state; logic that needs to run every
+    /// frame belongs in `update`.
     ///
     /// It is **highly recommended** that you call `clear_invalidate()` after the draw completes,
     /// otherwise, this will continue to be redrawn continuously (unless this is the desired
@@ -293,6 +506,61 @@ pub trait Widget {
     }
 }
 
+/// Sets a configuration value of a widget from outside of its setters, calling its
+/// `on_config_changed` function, and invalidating it.  This is used wherever a value is written
+/// for an arbitrary `ConfigKey`, such as by bindings, animations and layouts.
+pub fn set_widget_config<T: 'static>(widget: &mut dyn Widget, key: ConfigKey<T>, value: T) {
+    widget.config().set(key, value);
+    widget.on_config_changed(key.id());
+    widget.invalidate();
+}
+
+/// Removes a configuration value of a widget from outside of its setters, calling its
+/// `on_config_changed` function, and invalidating it.
+pub fn remove_widget_config<T: 'static>(widget: &mut dyn Widget, key: ConfigKey<T>) {
+    widget.config().remove(key);
+    widget.on_config_changed(key.id());
+    widget.invalidate();
+}
+
+/// Converts a `Widget` into `Any`, so that a `Widget` trait object can be downcast to its
+/// concrete type.  This is implemented automatically for all types.
+pub trait AsAny {
+    /// Retrieves a reference to this object as `Any`.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Retrieves a mutable reference to this object as `Any`.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A `Widget` that provides its settings as a strongly typed state struct, so that they can be
+/// read as plain fields instead of being looked up one by one in its `Configurable` store.
+///
+/// The widget keeps the state struct, which is the source of the settings it describes: the
+/// widget's setters update it, and values written by themes, parent widgets, bindings,
+/// animations and layouts reach it through `on_config_changed`.  The settings are also written
+/// to the `Configurable` store, from which layouts are saved.  Values set directly on the store
+/// with `Configurable::set` are not seen by the state; use `set_widget_config` instead.
+///
+/// The state of a widget held by a `WidgetStore` can be read with `WidgetStore::get_state`.
+pub trait StatefulWidget: Widget {
+    /// The type of the widget's state.
+    type State;
+
+    /// Retrieves the widget's current state.
+    fn state(&self) -> Self::State;
+}
+
 /// This is the `BaseWidget`, which contains a top-level widget for display.  It does
 /// not contain any special logic other than being a base for a display layer.
 pub struct BaseWidget {
@@ -309,6 +577,11 @@ impl BaseWidget {
             callbacks: CallbackStore::new(),
         }
     }
+
+    /// Creates a `WidgetBuilder` for a new `BaseWidget`.
+    pub fn builder() -> WidgetBuilder<Self> {
+        WidgetBuilder::new(Self::new())
+    }
 }
 
 /// Implementation of the `BaseWidget` object with the `Widget` traits implemented.
@@ -335,7 +608,7 @@ impl BaseWidget {
 ///    base_widget.set_color([0.5, 0.5, 0.5, 1.0]);
 ///
 ///    // Widgets must be boxed, as they are trait objects.
-///    let widget_id = prod.widget_store.add_widget(Box::new(base_widget));
+///    let widget_id = prod.widget_store.add_widget(Box::new(base_widget)).unwrap();
 ///
 ///    eprintln!("Added widget: ID={}", widget_id);
 ///
@@ -346,7 +619,9 @@ impl BaseWidget {
 ///    base_widget_2.set_color([0.75, 0.75, 0.75, 1.0]);
 ///
 ///    // Add the second widget to the top level base widget.
-///    let widget_id_2 = prod.widget_store.add_widget_to_parent(Box::new(base_widget_2), widget_id);
+///    let widget_id_2 = prod.widget_store
+///        .add_widget_to_parent(Box::new(base_widget_2), widget_id)
+///        .unwrap();
 /// # }
 /// ```
 impl Widget for BaseWidget {
@@ -354,7 +629,15 @@ impl Widget for BaseWidget {
         &mut self.config
     }
 
+    fn get_config(&self) -> &Configurable {
+        &self.config
+    }
+
     fn callbacks(&mut self) -> &mut CallbackStore {
         &mut self.callbacks
     }
+
+    fn get_widget_type(&self) -> &'static str {
+        "base"
+    }
 }